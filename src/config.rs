@@ -0,0 +1,796 @@
+use crate::license::LicensePolicy;
+use crate::metrics::{plugins, METRICS};
+use crate::output::Format;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+// weight of each metric in the net score, keyed by metric name, must sum to 1
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Weights {
+    // metrics missing from a profile weigh nothing, unless they are required
+    #[serde(flatten)]
+    pub metrics: BTreeMap<String, f64>,
+    // weights of the metrics of [[plugins]], by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub plugins: BTreeMap<String, f64>,
+}
+
+impl Default for Weights {
+    fn default() -> Weights {
+        Weights {
+            metrics: METRICS
+                .iter()
+                .map(|m| (m.name.to_string(), m.weight))
+                .collect(),
+            plugins: BTreeMap::new(),
+        }
+    }
+}
+
+impl Weights {
+    // weight of a metric, 0 if the profile doesn't give one
+    pub fn get(&self, name: &str) -> f64 {
+        self.metrics.get(name).copied().unwrap_or(0.0)
+    }
+
+    // every metric is known, required ones are given,
+    // every weight is in [0, 1] and they add up to 1
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(name) = self
+            .metrics
+            .keys()
+            .find(|n| !METRICS.iter().any(|m| &m.name == n))
+        {
+            return Err(format!("unknown metric {}", name));
+        }
+        if let Some(m) = METRICS
+            .iter()
+            .find(|m| m.required && !self.metrics.contains_key(m.name))
+        {
+            return Err(format!("missing weight of {}", m.name));
+        }
+        let named: Vec<(&String, &f64)> = self.metrics.iter().chain(&self.plugins).collect();
+        if let Some((name, w)) = named
+            .iter()
+            .find(|(_, w)| !w.is_finite() || **w < 0.0 || **w > 1.0)
+        {
+            return Err(format!(
+                "weight of {} must be between 0 and 1, got {}",
+                name, w
+            ));
+        }
+        let sum: f64 = named.iter().map(|(_, w)| *w).sum();
+        if (sum - 1.0).abs() > 1e-6 {
+            return Err(format!("weights must sum to 1, got {}", sum));
+        }
+        Ok(())
+    }
+}
+
+// named set of weights used to calculate the net score
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub weights: Weights,
+}
+
+// name of the profile used when none is selected
+pub const DEFAULT_PROFILE: &str = "default";
+
+// settings of the github api
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GithubConfig {
+    // personal access token, from $GITHUB_TOKEN by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    pub api_url: String,
+    pub graphql_url: String,
+}
+
+impl Default for GithubConfig {
+    fn default() -> GithubConfig {
+        GithubConfig {
+            token: None,
+            api_url: "https://api.github.com".to_string(),
+            graphql_url: "https://api.github.com/graphql".to_string(),
+        }
+    }
+}
+
+// settings of the npm registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NpmConfig {
+    pub registry_url: String,
+    pub downloads_url: String,
+    // deps.dev API counting the dependents of a version
+    pub dependents_url: String,
+}
+
+impl Default for NpmConfig {
+    fn default() -> NpmConfig {
+        NpmConfig {
+            registry_url: "https://registry.npmjs.org".to_string(),
+            downloads_url: "https://api.npmjs.org/downloads".to_string(),
+            dependents_url: "https://api.deps.dev/v3alpha".to_string(),
+        }
+    }
+}
+
+// settings of the crates.io api
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CratesConfig {
+    pub api_url: String,
+}
+
+impl Default for CratesConfig {
+    fn default() -> CratesConfig {
+        CratesConfig {
+            api_url: "https://crates.io/api/v1".to_string(),
+        }
+    }
+}
+
+// automated accounts besides GitHub apps, left out of every metric
+// that counts people or pull requests
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotsConfig {
+    // GitHub logins, or names and emails of commit authors
+    pub accounts: Vec<String>,
+}
+
+// external command scoring a custom metric, see metrics::plugins
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    // lowercase letters, digits and underscores, e.g. "approved_vendor"
+    pub name: String,
+    // program and its arguments
+    pub command: Vec<String>,
+    // the plugin is killed and scores 0 after this many seconds
+    #[serde(default = "default_plugin_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_plugin_timeout() -> u64 {
+    60
+}
+
+// inputs of ramp-up besides the README
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RampUpConfig {
+    // share of the score taken from the size and complexity of the code base,
+    // 0 to score the README only
+    pub codebase_weight: f64,
+}
+
+// bug reports considered by correctness
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorrectnessConfig {
+    // bugs reported within this many days are counted
+    pub window_days: u32,
+    // issues with any of these labels are bugs
+    pub bug_labels: Vec<String>,
+}
+
+impl Default for CorrectnessConfig {
+    fn default() -> CorrectnessConfig {
+        CorrectnessConfig {
+            window_days: 365,
+            bug_labels: vec!["bug".to_string()],
+        }
+    }
+}
+
+// truck factor from commit history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BusFactorConfig {
+    // commits within this many days of the evaluated commit are counted
+    pub window_days: u32,
+    // share of those commits the truck factor's authors must cover
+    pub share: f64,
+    // authors ignored by the bus factor only, on top of [bots]
+    pub bots: Vec<String>,
+}
+
+impl Default for BusFactorConfig {
+    fn default() -> BusFactorConfig {
+        BusFactorConfig {
+            window_days: 365,
+            share: 0.5,
+            bots: Vec::new(),
+        }
+    }
+}
+
+// response and close times of issues and pull requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResponsivenessConfig {
+    // issues and pull requests created within this many days are counted
+    pub window_days: u32,
+    // most recent issues, and pull requests, fetched (at most 100)
+    pub sample: u32,
+}
+
+impl Default for ResponsivenessConfig {
+    fn default() -> ResponsivenessConfig {
+        ResponsivenessConfig {
+            window_days: 365,
+            sample: 100,
+        }
+    }
+}
+
+// commits whose review coverage is measured
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReviewConfig {
+    // commits to the default branch within this many days are counted
+    pub window_days: u32,
+    // most recent commits fetched
+    pub max_commits: u32,
+}
+
+impl Default for ReviewConfig {
+    fn default() -> ReviewConfig {
+        ReviewConfig {
+            window_days: 90,
+            max_commits: 500,
+        }
+    }
+}
+
+// local advisory database, e.g. an OSV export unpacked into a directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdvisoriesConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+}
+
+// logging, from $LOG_LEVEL and $LOG_FILE by default
+// level 0 is off, 1 is info and 2 is debug
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub level: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+// defaults of the url command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    pub format: Format,
+    pub profile: String,
+}
+
+impl Default for OutputConfig {
+    fn default() -> OutputConfig {
+        OutputConfig {
+            format: Format::Ndjson,
+            profile: DEFAULT_PROFILE.to_string(),
+        }
+    }
+}
+
+// effective configuration of the CLI
+// built from the layers returned by `layer_paths`, the --config file,
+// environment variables and command line flags, later layers taking precedence
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // directory repositories are cloned into
+    pub cache_dir: PathBuf,
+    // number of modules scored at the same time
+    pub concurrency: usize,
+    // file with the license policy, replacing the [license] table
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license_policy: Option<String>,
+    // levels of dependencies whose licenses are checked, 0 to skip them
+    pub license_depth: usize,
+
+    pub github: GithubConfig,
+    pub npm: NpmConfig,
+    pub crates: CratesConfig,
+    pub bots: BotsConfig,
+    pub ramp_up: RampUpConfig,
+    pub correctness: CorrectnessConfig,
+    pub bus_factor: BusFactorConfig,
+    pub responsiveness: ResponsivenessConfig,
+    pub review: ReviewConfig,
+    pub advisories: AdvisoriesConfig,
+    pub log: LogConfig,
+    pub output: OutputConfig,
+    pub license: LicensePolicy,
+
+    // custom metrics, e.g.
+    //
+    // [[plugins]]
+    // name = "approved_vendor"
+    // command = ["python3", "vendor_check.py"]
+    pub plugins: Vec<PluginConfig>,
+
+    // scoring profiles, e.g.
+    //
+    // [profiles.compliance]
+    // ramp_up = 0.0
+    // correctness = 0.1
+    // bus_factor = 0.1
+    // responsiveness = 0.1
+    // license = 0.7
+    pub profiles: BTreeMap<String, Weights>,
+
+    // files the configuration was read from
+    #[serde(skip)]
+    pub sources: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            cache_dir: std::env::temp_dir().join("ece461_team19_cli"),
+            concurrency: 1,
+            license_policy: None,
+            license_depth: 0,
+            github: GithubConfig::default(),
+            npm: NpmConfig::default(),
+            crates: CratesConfig::default(),
+            bots: BotsConfig::default(),
+            ramp_up: RampUpConfig::default(),
+            correctness: CorrectnessConfig::default(),
+            bus_factor: BusFactorConfig::default(),
+            responsiveness: ResponsivenessConfig::default(),
+            review: ReviewConfig::default(),
+            advisories: AdvisoriesConfig::default(),
+            log: LogConfig::default(),
+            output: OutputConfig::default(),
+            license: LicensePolicy::default(),
+            plugins: Vec::new(),
+            profiles: BTreeMap::new(),
+            sources: Vec::new(),
+        }
+    }
+}
+
+// configuration files from lowest to highest precedence:
+// system, user, then project-local
+pub fn layer_paths() -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from("/etc/ece461_team19_cli/config.toml")];
+    let user_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")));
+    if let Some(dir) = user_dir {
+        paths.push(dir.join("ece461_team19_cli").join("config.toml"));
+    }
+    paths.push(PathBuf::from(".ece461_team19_cli.toml"));
+    paths
+}
+
+// settings taken from environment variables
+pub fn env_layer<F: Fn(&str) -> Option<String>>(var: F) -> toml::Value {
+    let mut layer = toml::Table::new();
+    if let Some(token) = var("GITHUB_TOKEN") {
+        let mut github = toml::Table::new();
+        github.insert("token".to_string(), token.into());
+        layer.insert("github".to_string(), github.into());
+    }
+    let mut log = toml::Table::new();
+    if let Some(level) = var("LOG_LEVEL").and_then(|l| l.parse::<u8>().ok()) {
+        log.insert("level".to_string(), i64::from(level).into());
+    }
+    if let Some(file) = var("LOG_FILE") {
+        log.insert("file".to_string(), file.into());
+    }
+    if !log.is_empty() {
+        layer.insert("log".to_string(), log.into());
+    }
+    layer.into()
+}
+
+// recursively merge tables of `layer` into `base`, other values are replaced
+fn merge(base: &mut toml::Value, layer: toml::Value) {
+    match (base, layer) {
+        (toml::Value::Table(base), toml::Value::Table(layer)) => {
+            for (k, v) in layer {
+                match base.get_mut(&k) {
+                    Some(b) => merge(b, v),
+                    None => {
+                        base.insert(k, v);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+fn parse_layer(s: &str) -> Result<toml::Value, String> {
+    s.parse::<toml::Table>()
+        .map(toml::Value::Table)
+        .map_err(|e| format!("{}", e))
+}
+
+impl Config {
+    // merge layers from lowest to highest precedence
+    pub fn from_layers(layers: Vec<toml::Value>) -> Result<Config, String> {
+        let mut merged = toml::Value::Table(toml::Table::new());
+        for layer in layers {
+            merge(&mut merged, layer);
+        }
+        let config: Config = merged.try_into().map_err(|e| format!("{}", e))?;
+        for (i, plugin) in config.plugins.iter().enumerate() {
+            let name = &plugin.name;
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+            {
+                return Err(format!(
+                    "plugin name {:?} must be lowercase letters, digits and underscores",
+                    name
+                ));
+            }
+            // plugin scores are output next to the built-in ones
+            let column = plugins::column(name);
+            if column == "NET_SCORE"
+                || METRICS.iter().any(|m| m.name == name || m.column == column)
+                || config.plugins[..i].iter().any(|p| &p.name == name)
+            {
+                return Err(format!("plugin {}: name is already taken", name));
+            }
+            if plugin.command.is_empty() {
+                return Err(format!("plugin {}: command is empty", name));
+            }
+            if plugin.timeout_secs == 0 {
+                return Err(format!("plugin {}: timeout_secs must be at least 1", name));
+            }
+        }
+        for (name, weights) in &config.profiles {
+            weights
+                .validate()
+                .map_err(|e| format!("profile {}: {}", name, e))?;
+            if let Some(p) = weights
+                .plugins
+                .keys()
+                .find(|p| !config.plugins.iter().any(|c| &c.name == *p))
+            {
+                return Err(format!("profile {}: unknown plugin {}", name, p));
+            }
+        }
+        if config.concurrency == 0 {
+            return Err("concurrency must be at least 1".to_string());
+        }
+        let w = config.ramp_up.codebase_weight;
+        if !(0.0..=1.0).contains(&w) {
+            return Err(format!("ramp_up: codebase_weight {} is not in [0, 1]", w));
+        }
+        if config.correctness.bug_labels.is_empty() {
+            return Err("correctness: bug_labels is empty".to_string());
+        }
+        let share = config.bus_factor.share;
+        if !(share > 0.0 && share <= 1.0) {
+            return Err(format!("bus_factor: share {} is not in (0, 1]", share));
+        }
+        let sample = config.responsiveness.sample;
+        if !(1..=100).contains(&sample) {
+            return Err(format!(
+                "responsiveness: sample {} is not in 1..=100",
+                sample
+            ));
+        }
+        config
+            .license
+            .validate()
+            .map_err(|e| format!("license: {}", e))?;
+        Ok(config)
+    }
+
+    // read the configuration files, the optional file given with --config
+    // and the environment
+    pub fn load(explicit: Option<&str>) -> Result<Config, String> {
+        let mut layers = Vec::new();
+        let mut sources = Vec::new();
+        for path in layer_paths() {
+            if let Ok(s) = std::fs::read_to_string(&path) {
+                let p = path.display().to_string();
+                layers.push(parse_layer(&s).map_err(|e| format!("{}: {}", p, e))?);
+                sources.push(p);
+            }
+        }
+        if let Some(path) = explicit {
+            let s = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            layers.push(parse_layer(&s).map_err(|e| format!("{}: {}", path, e))?);
+            sources.push(path.to_string());
+        }
+        layers.push(env_layer(|v| std::env::var(v).ok()));
+
+        let mut config = Config::from_layers(layers)?;
+        config.sources = sources;
+        config.load_license_policy()?;
+        Ok(config)
+    }
+
+    // replace the [license] table with the policy file, if one is set
+    pub fn load_license_policy(&mut self) -> Result<(), String> {
+        if let Some(path) = &self.license_policy {
+            self.license = LicensePolicy::load(path)?;
+        }
+        Ok(())
+    }
+
+    // look up a profile by name
+    // the default profile uses the built-in weights unless the file overrides it
+    pub fn profile(&self, name: &str) -> Result<Profile, String> {
+        let weights = match self.profiles.get(name) {
+            Some(w) => w.clone(),
+            None if name == DEFAULT_PROFILE => Weights::default(),
+            None => return Err(format!("unknown scoring profile: {}", name)),
+        };
+        Ok(Profile {
+            name: name.to_string(),
+            weights,
+        })
+    }
+
+    // effective configuration as TOML, with the token hidden
+    pub fn show(&self) -> Result<String, String> {
+        let mut shown = self.clone();
+        if shown.github.token.is_some() {
+            shown.github.token = Some("<hidden>".to_string());
+        }
+        let mut out = String::new();
+        for source in &self.sources {
+            out += &format!("# from {}\n", source);
+        }
+        out += &toml::to_string_pretty(&shown).map_err(|e| format!("{}", e))?;
+        Ok(out)
+    }
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+// make the configuration available through `get`
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+// effective configuration
+// without `init`, only defaults and environment variables are used
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(|| {
+        Config::from_layers(vec![env_layer(|v| std::env::var(v).ok())]).unwrap_or_default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_toml(s: &str) -> Result<Config, String> {
+        Config::from_layers(vec![parse_layer(s)?])
+    }
+
+    #[test]
+    fn default_weights_are_valid() {
+        let w = Weights::default();
+        assert!(w.validate().is_ok());
+        assert_eq!(w.get("responsiveness"), 0.15);
+        assert_eq!(w.get("license"), 0.4);
+        assert_eq!(w.get("pinning"), 0.1);
+        assert_eq!(w.get("maintenance"), 0.1);
+    }
+
+    #[test]
+    fn invalid_weights() {
+        let with = |weights: &[(&str, f64)]| {
+            let mut w = Weights::default();
+            for (name, x) in weights {
+                w.metrics.insert(name.to_string(), *x);
+            }
+            w.validate()
+        };
+        assert!(with(&[("license", 0.6)]).unwrap_err().contains("sum to 1"));
+        assert!(with(&[("license", 1.5), ("responsiveness", -0.75)])
+            .unwrap_err()
+            .contains("between 0 and 1"));
+        assert!(with(&[("license", f64::NAN)]).is_err());
+        assert!(with(&[("speed", 0.0)])
+            .unwrap_err()
+            .contains("unknown metric speed"));
+    }
+
+    #[test]
+    fn profiles_from_toml() {
+        let config = from_toml(
+            "[profiles.compliance]\n\
+             ramp_up = 0.0\n\
+             correctness = 0.1\n\
+             bus_factor = 0.1\n\
+             responsiveness = 0.1\n\
+             license = 0.7\n",
+        )
+        .unwrap();
+
+        let p = config.profile("compliance").unwrap();
+        assert_eq!(p.name, "compliance");
+        assert_eq!(p.weights.get("license"), 0.7);
+        assert_eq!(p.weights.get("testing"), 0.0);
+
+        // the default profile is always available
+        assert_eq!(
+            config.profile("default").unwrap().weights,
+            Weights::default()
+        );
+        assert!(config.profile("missing").is_err());
+    }
+
+    #[test]
+    fn layers_override_lower_layers() {
+        let system = parse_layer(
+            "concurrency = 2\n\
+             [github]\n\
+             api_url = \"https://github.example.com/api/v3\"\n\
+             [output]\n\
+             format = \"csv\"\n",
+        )
+        .unwrap();
+        let project = parse_layer("[output]\nprofile = \"compliance\"\n").unwrap();
+        let env = env_layer(|v| match v {
+            "GITHUB_TOKEN" => Some("secret".to_string()),
+            "LOG_LEVEL" => Some("2".to_string()),
+            _ => None,
+        });
+        let config = Config::from_layers(vec![system, project, env]).unwrap();
+
+        assert_eq!(config.concurrency, 2);
+        assert_eq!(config.github.api_url, "https://github.example.com/api/v3");
+        assert_eq!(config.github.graphql_url, "https://api.github.com/graphql");
+        assert_eq!(config.github.token.as_deref(), Some("secret"));
+        assert_eq!(config.output.format, Format::Csv);
+        assert_eq!(config.output.profile, "compliance");
+        assert_eq!(config.log.level, 2);
+        assert!(config.log.file.is_none());
+    }
+
+    #[test]
+    fn env_layer_ignores_bad_values() {
+        let env = env_layer(|v| match v {
+            "LOG_LEVEL" => Some("verbose".to_string()),
+            _ => None,
+        });
+        assert_eq!(env, toml::Value::Table(toml::Table::new()));
+    }
+
+    #[test]
+    fn plugins_from_toml() {
+        let config = from_toml(
+            "[[plugins]]\n\
+             name = \"approved_vendor\"\n\
+             command = [\"python3\", \"vendor_check.py\"]\n\
+             [profiles.vendor]\n\
+             ramp_up = 0.0\n\
+             correctness = 0.1\n\
+             bus_factor = 0.1\n\
+             responsiveness = 0.1\n\
+             license = 0.5\n\
+             [profiles.vendor.plugins]\n\
+             approved_vendor = 0.2\n",
+        )
+        .unwrap();
+        assert_eq!(config.plugins[0].command, ["python3", "vendor_check.py"]);
+        assert_eq!(config.plugins[0].timeout_secs, 60);
+        let p = config.profile("vendor").unwrap();
+        assert_eq!(p.weights.plugins["approved_vendor"], 0.2);
+
+        // plugin weights count towards the sum
+        assert!(from_toml(
+            "[[plugins]]\nname = \"a\"\ncommand = [\"true\"]\n\
+             [profiles.p]\nramp_up = 0.05\ncorrectness = 0.1\nbus_factor = 0.1\n\
+             responsiveness = 0.25\nlicense = 0.5\n[profiles.p.plugins]\na = 0.5\n"
+        )
+        .unwrap_err()
+        .contains("sum to 1"));
+    }
+
+    #[test]
+    fn bad_plugins() {
+        let plugin = |name: &str, command: &str| {
+            from_toml(&format!(
+                "[[plugins]]\nname = \"{}\"\ncommand = {}\n",
+                name, command
+            ))
+        };
+        assert!(plugin("ok_1", "[\"true\"]").is_ok());
+        assert!(plugin("Vendor", "[\"true\"]").is_err());
+        assert!(plugin("license", "[\"true\"]").is_err());
+        assert!(plugin("net", "[\"true\"]").is_err());
+        assert!(plugin("vulnerability", "[\"true\"]").is_err());
+        assert!(plugin("responsive_maintainer", "[\"true\"]").is_err());
+        assert!(plugin("ok", "[]").is_err());
+        assert!(from_toml(
+            "[[plugins]]\nname = \"a\"\ncommand = [\"true\"]\n\
+             [[plugins]]\nname = \"a\"\ncommand = [\"false\"]\n"
+        )
+        .is_err());
+        assert!(from_toml(
+            "[profiles.p]\nramp_up = 0.0\ncorrectness = 0.1\nbus_factor = 0.1\n\
+             responsiveness = 0.25\nlicense = 0.5\n[profiles.p.plugins]\nmissing = 0.05\n"
+        )
+        .unwrap_err()
+        .contains("unknown plugin missing"));
+    }
+
+    #[test]
+    fn bad_settings() {
+        assert!(from_toml("concurrency = 0\n").is_err());
+        assert!(from_toml("[ramp_up]\ncodebase_weight = 1.5\n").is_err());
+        assert!(from_toml("[correctness]\nbug_labels = []\n").is_err());
+        assert!(from_toml("[bus_factor]\nshare = 0.0\n").is_err());
+        assert!(from_toml("[responsiveness]\nsample = 101\n").is_err());
+        assert!(from_toml("[github]\nendpoint = \"x\"\n").is_err());
+        assert!(from_toml("[output]\nformat = \"xml\"\n").is_err());
+    }
+
+    #[test]
+    fn show_hides_token() {
+        let mut config = from_toml(
+            "[profiles.compliance]\n\
+             ramp_up = 0.0\n\
+             correctness = 0.1\n\
+             bus_factor = 0.1\n\
+             responsiveness = 0.1\n\
+             license = 0.7\n",
+        )
+        .unwrap();
+        config.github.token = Some("secret".to_string());
+        let shown = config.show().unwrap();
+        assert!(!shown.contains("secret"));
+        assert!(shown.contains("<hidden>"));
+
+        // the shown configuration can be read back
+        let read = from_toml(&shown).unwrap();
+        assert_eq!(read.profiles, config.profiles);
+        assert_eq!(read.license, config.license);
+    }
+
+    #[test]
+    fn bad_profiles() {
+        // weights don't sum to 1
+        let err = from_toml(
+            "[profiles.bad]\n\
+             ramp_up = 0.5\n\
+             correctness = 0.5\n\
+             bus_factor = 0.5\n\
+             responsiveness = 0.5\n\
+             license = 0.5\n",
+        )
+        .unwrap_err();
+        assert!(err.starts_with("profile bad:"));
+
+        // missing and misspelled weights
+        assert!(from_toml("[profiles.bad]\nramp_up = 1.0\n")
+            .unwrap_err()
+            .contains("missing weight of correctness"));
+        assert!(from_toml(
+            "[profiles.bad]\n\
+             rampup = 0.05\n\
+             correctness = 0.1\n\
+             bus_factor = 0.1\n\
+             responsiveness = 0.25\n\
+             license = 0.5\n"
+        )
+        .unwrap_err()
+        .contains("unknown metric rampup"));
+    }
+}
@@ -0,0 +1,340 @@
+pub mod classify;
+pub mod deps;
+pub mod spdx;
+
+use serde::{Deserialize, Serialize};
+use spdx::Expr;
+
+// what the policy says about a license
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Allow,
+    Review,
+    Deny,
+}
+
+// per-package override of the license lists
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Exception {
+    // npm package name or github "owner/repo"
+    pub package: String,
+    // only applies to this license if set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    pub decision: Decision,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+// license policy of a product, e.g.
+//
+// allow = ["MIT", "BSD-3-Clause"]
+// review = ["MPL-2.0"]
+// deny = ["GPL-3.0-only"]
+// unlisted = "deny"
+//
+// [[exceptions]]
+// package = "left-pad"
+// decision = "allow"
+// reason = "approved by legal"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LicensePolicy {
+    pub allow: Vec<String>,
+    pub review: Vec<String>,
+    pub deny: Vec<String>,
+    // decision for licenses on none of the lists
+    pub unlisted: Decision,
+    // decision for NOASSERTION, NONE and unparsable licenses
+    pub unknown: Decision,
+    // LICENSE_SCORE of licenses that need review
+    pub review_score: f64,
+    pub exceptions: Vec<Exception>,
+}
+
+impl Default for LicensePolicy {
+    fn default() -> LicensePolicy {
+        // compatible with LGPLv2.1
+        let allow = [
+            "LGPL-2.1-only",
+            "LGPL-2.1",
+            "LGPL-2.1-or-later",
+            "LGPL-3.0-only",
+            "LGPL-3.0",
+            "BSD-3-Clause",
+            "MIT",
+            "X11",
+            "CC0-1.0",
+            "Unlicense",
+        ];
+        LicensePolicy {
+            allow: allow.iter().map(|l| l.to_string()).collect(),
+            review: Vec::new(),
+            deny: Vec::new(),
+            unlisted: Decision::Deny,
+            unknown: Decision::Deny,
+            review_score: 0.5,
+            exceptions: Vec::new(),
+        }
+    }
+}
+
+// decision of the policy and the rule that made it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verdict {
+    pub decision: Decision,
+    pub rule: String,
+}
+
+// SPDX ids are matched case-insensitively
+fn listed(list: &[String], license: &str) -> bool {
+    list.iter().any(|l| l.eq_ignore_ascii_case(license))
+}
+
+impl LicensePolicy {
+    pub fn load(path: &str) -> Result<LicensePolicy, String> {
+        let s = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let policy: LicensePolicy = toml::from_str(&s).map_err(|e| format!("{}: {}", path, e))?;
+        policy.validate().map_err(|e| format!("{}: {}", path, e))?;
+        Ok(policy)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.review_score) {
+            return Err(format!(
+                "review_score must be between 0 and 1, got {}",
+                self.review_score
+            ));
+        }
+        Ok(())
+    }
+
+    // decide on the license expression of a package known by any of `packages`
+    // exceptions are checked first, then deny, review and allow lists
+    // OR picks the most favorable term and AND the least favorable one
+    pub fn evaluate(&self, packages: &[&str], license: &str) -> Verdict {
+        let license = license.trim();
+        if let Some(v) = self.lookup(packages, license) {
+            return v;
+        }
+        if license.is_empty()
+            || license.eq_ignore_ascii_case("NOASSERTION")
+            || license.eq_ignore_ascii_case("NONE")
+        {
+            return Verdict {
+                decision: self.unknown,
+                rule: format!("unknown: {}", license),
+            };
+        }
+        match spdx::parse(license) {
+            Ok(expr) => self.evaluate_expr(packages, &expr),
+            Err(e) => Verdict {
+                decision: self.unknown,
+                rule: format!("unknown: {}", e),
+            },
+        }
+    }
+
+    fn evaluate_expr(&self, packages: &[&str], expr: &Expr) -> Verdict {
+        match expr {
+            Expr::License {
+                id,
+                plus,
+                exception,
+            } => {
+                // most specific form first, e.g. "GPL-2.0+ WITH Classpath-exception-2.0",
+                // then "GPL-2.0+" and "GPL-2.0"
+                let mut keys = vec![expr.to_string()];
+                if exception.is_some() && *plus {
+                    keys.push(format!("{}+", id));
+                }
+                keys.push(id.clone());
+                keys.iter()
+                    .find_map(|k| self.lookup(packages, k))
+                    .unwrap_or(Verdict {
+                        decision: self.unlisted,
+                        rule: format!("unlisted: {}", expr),
+                    })
+            }
+            Expr::Or(terms) => terms
+                .iter()
+                .map(|t| self.evaluate_expr(packages, t))
+                .max_by(|a, b| self.score(a.decision).total_cmp(&self.score(b.decision)))
+                .unwrap(),
+            Expr::And(terms) => terms
+                .iter()
+                .map(|t| self.evaluate_expr(packages, t))
+                .min_by(|a, b| self.score(a.decision).total_cmp(&self.score(b.decision)))
+                .unwrap(),
+        }
+    }
+
+    // decision of an exception or list naming the license, if any
+    fn lookup(&self, packages: &[&str], license: &str) -> Option<Verdict> {
+        let exception = self.exceptions.iter().find(|e| {
+            packages.contains(&e.package.as_str())
+                && e.license
+                    .as_ref()
+                    .is_none_or(|l| l.eq_ignore_ascii_case(license))
+        });
+        if let Some(e) = exception {
+            return Some(Verdict {
+                decision: e.decision,
+                rule: format!("exception: {}", e.package),
+            });
+        }
+
+        let lists = [
+            (Decision::Deny, &self.deny, "deny"),
+            (Decision::Review, &self.review, "review"),
+            (Decision::Allow, &self.allow, "allow"),
+        ];
+        lists
+            .into_iter()
+            .find(|(_, list, _)| listed(list, license))
+            .map(|(decision, _, name)| Verdict {
+                decision,
+                rule: format!("{}: {}", name, license),
+            })
+    }
+
+    // LICENSE_SCORE of a decision
+    pub fn score(&self, decision: Decision) -> f64 {
+        match decision {
+            Decision::Allow => 1.0,
+            Decision::Review => self.review_score,
+            Decision::Deny => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> LicensePolicy {
+        toml::from_str(
+            "allow = [\"MIT\", \"Apache-2.0\"]\n\
+             review = [\"MPL-2.0\"]\n\
+             deny = [\"GPL-3.0-only\", \"MIT\"]\n\
+             unlisted = \"review\"\n\
+             [[exceptions]]\n\
+             package = \"left-pad\"\n\
+             decision = \"allow\"\n\
+             [[exceptions]]\n\
+             package = \"owner/repo\"\n\
+             license = \"GPL-3.0-only\"\n\
+             decision = \"review\"\n\
+             reason = \"internal tool only\"\n",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn default_policy() {
+        let p = LicensePolicy::default();
+        assert_eq!(p.evaluate(&[], "MIT").decision, Decision::Allow);
+        assert_eq!(p.evaluate(&[], "mit").decision, Decision::Allow);
+        assert_eq!(p.evaluate(&[], "Apache-2.0").decision, Decision::Deny);
+        assert_eq!(p.evaluate(&[], "Apache-2.0").rule, "unlisted: Apache-2.0");
+        assert_eq!(p.evaluate(&[], "NOASSERTION").decision, Decision::Deny);
+    }
+
+    #[test]
+    fn expressions() {
+        let p = policy();
+
+        // most favorable choice
+        let v = p.evaluate(&["pkg"], "MIT OR Apache-2.0");
+        assert_eq!(v.decision, Decision::Allow);
+        assert_eq!(v.rule, "allow: Apache-2.0");
+        assert_eq!(
+            p.evaluate(&["pkg"], "GPL-3.0-only OR MPL-2.0").decision,
+            Decision::Review
+        );
+
+        // every term is required
+        let v = p.evaluate(&["pkg"], "(Apache-2.0 AND GPL-3.0-only)");
+        assert_eq!(v.decision, Decision::Deny);
+        assert_eq!(v.rule, "deny: GPL-3.0-only");
+        assert_eq!(
+            p.evaluate(&["pkg"], "Apache-2.0 AND (MPL-2.0 OR GPL-3.0-only)")
+                .decision,
+            Decision::Review
+        );
+    }
+
+    #[test]
+    fn with_exceptions() {
+        let mut p = policy();
+        // falls back to the license itself
+        let v = p.evaluate(&["pkg"], "GPL-3.0-only WITH Classpath-exception-2.0");
+        assert_eq!(v.decision, Decision::Deny);
+
+        // unless the combination is listed
+        p.allow
+            .push("GPL-3.0-only WITH Classpath-exception-2.0".to_string());
+        let v = p.evaluate(&["pkg"], "GPL-3.0-only WITH Classpath-exception-2.0");
+        assert_eq!(v.decision, Decision::Allow);
+    }
+
+    #[test]
+    fn unknown_licenses() {
+        let mut p = policy();
+        p.unknown = Decision::Review;
+        assert_eq!(
+            p.evaluate(&["pkg"], "NOASSERTION").decision,
+            Decision::Review
+        );
+        assert_eq!(p.evaluate(&["pkg"], "MIT OR (").decision, Decision::Review);
+        assert_eq!(p.evaluate(&["pkg"], "").rule, "unknown: ");
+    }
+
+    #[test]
+    fn lists() {
+        let p = policy();
+        let v = p.evaluate(&["pkg"], "Apache-2.0");
+        assert_eq!(v.decision, Decision::Allow);
+        assert_eq!(v.rule, "allow: Apache-2.0");
+
+        assert_eq!(p.evaluate(&["pkg"], "MPL-2.0").decision, Decision::Review);
+        assert_eq!(p.evaluate(&["pkg"], "ISC").decision, Decision::Review);
+
+        // deny wins over allow
+        let v = p.evaluate(&["pkg"], "MIT");
+        assert_eq!(v.decision, Decision::Deny);
+        assert_eq!(v.rule, "deny: MIT");
+    }
+
+    #[test]
+    fn exceptions() {
+        let p = policy();
+        let v = p.evaluate(&["left-pad"], "GPL-3.0-only");
+        assert_eq!(v.decision, Decision::Allow);
+        assert_eq!(v.rule, "exception: left-pad");
+
+        // exception limited to one license
+        let v = p.evaluate(&["repo", "owner/repo"], "GPL-3.0-only");
+        assert_eq!(v.decision, Decision::Review);
+        let v = p.evaluate(&["owner/repo"], "MIT");
+        assert_eq!(v.decision, Decision::Deny);
+    }
+
+    #[test]
+    fn scores() {
+        let p = policy();
+        assert_eq!(p.score(Decision::Allow), 1.0);
+        assert_eq!(p.score(Decision::Review), 0.5);
+        assert_eq!(p.score(Decision::Deny), 0.0);
+    }
+
+    #[test]
+    fn bad_policy() {
+        assert!(toml::from_str::<LicensePolicy>("unlisted = \"maybe\"\n").is_err());
+        assert!(toml::from_str::<LicensePolicy>("acceptable = []\n").is_err());
+        let p: LicensePolicy = toml::from_str("review_score = 2.0\n").unwrap();
+        assert!(p.validate().is_err());
+    }
+}
@@ -0,0 +1,258 @@
+// offline license detection by comparing files in a repository
+// against bundled SPDX license texts
+use std::collections::HashSet;
+use std::path::Path;
+
+// bundled corpus, named with the ids github reports
+// long licenses are represented by their opening sections, so for them
+// THRESHOLD applies to that opening only: a file quoting just the opening,
+// such as a notice pointing at the full text, still matches, and changes to
+// the terms further down go unnoticed
+const CORPUS: &[(&str, &str)] = &[
+    ("0BSD", include_str!("texts/0BSD.txt")),
+    ("Apache-2.0", include_str!("texts/Apache-2.0.txt")),
+    ("BSD-2-Clause", include_str!("texts/BSD-2-Clause.txt")),
+    ("BSD-3-Clause", include_str!("texts/BSD-3-Clause.txt")),
+    ("CC0-1.0", include_str!("texts/CC0-1.0.txt")),
+    ("GPL-2.0", include_str!("texts/GPL-2.0.txt")),
+    ("GPL-3.0", include_str!("texts/GPL-3.0.txt")),
+    ("ISC", include_str!("texts/ISC.txt")),
+    ("LGPL-2.1", include_str!("texts/LGPL-2.1.txt")),
+    ("LGPL-3.0", include_str!("texts/LGPL-3.0.txt")),
+    ("MIT", include_str!("texts/MIT.txt")),
+    ("MPL-2.0", include_str!("texts/MPL-2.0.txt")),
+    ("Unlicense", include_str!("texts/Unlicense.txt")),
+];
+
+// share of a license text that must be found in a file to count as a match
+pub const THRESHOLD: f64 = 0.75;
+
+// license files are not searched deeper than this
+const MAX_DEPTH: usize = 3;
+// files larger than this are not license texts
+const MAX_SIZE: u64 = 256 * 1024;
+
+// license found in a file of the repository
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub license: &'static str,
+    // path relative to the repository root
+    pub file: String,
+    // share of the license text found in the file, in [0, 1]
+    pub confidence: f64,
+}
+
+// consecutive word triples of the text, ignoring case and punctuation
+fn shingles(text: &str) -> HashSet<String> {
+    let lower = text.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    words.windows(3).map(|w| w.join(" ")).collect()
+}
+
+// best matching license of a text and the share of the license text found in it
+// when several licenses are (nearly) fully contained, e.g. BSD-2-Clause in a
+// BSD-3-Clause file, the longer and so more specific license wins
+pub fn classify_text(text: &str) -> Option<(&'static str, f64)> {
+    let file = shingles(text);
+    let mut best: Option<(&'static str, f64, usize)> = None;
+    for (license, template) in CORPUS {
+        let t = shingles(template);
+        if t.is_empty() {
+            continue;
+        }
+        let confidence = t.intersection(&file).count() as f64 / t.len() as f64;
+        let better = match best {
+            None => true,
+            Some((_, c, len)) => {
+                confidence > c + 0.02 || ((confidence - c).abs() <= 0.02 && t.len() > len)
+            }
+        };
+        if better {
+            best = Some((license, confidence, t.len()));
+        }
+    }
+    best.filter(|(_, c, _)| *c >= THRESHOLD)
+        .map(|(license, c, _)| (license, c))
+}
+
+// LICENSE, LICENCE, COPYING and UNLICENSE files, with or without extension
+fn is_license_file(name: &str) -> bool {
+    let name = name.to_lowercase();
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((s, e)) => (s.to_string(), e.to_string()),
+        None => (name.clone(), String::new()),
+    };
+    let named = ["license", "licence", "copying", "unlicense"]
+        .iter()
+        .any(|p| stem.starts_with(p));
+    named
+        && [
+            "", "txt", "md", "rst", "lesser", "mit", "apache", "bsd", "gpl",
+        ]
+        .contains(&ext.as_str())
+}
+
+fn walk(root: &Path, dir: &Path, depth: usize, found: &mut Vec<Match>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+    let mut entries: Vec<_> = entries.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let meta = match entry.metadata() {
+            Ok(m) => m,
+            Err(_) => continue,
+        };
+        if meta.is_dir() {
+            if depth < MAX_DEPTH && name != ".git" && name != "node_modules" {
+                walk(root, &path, depth + 1, found);
+            }
+        } else if is_license_file(&name) && meta.len() <= MAX_SIZE {
+            let text = match std::fs::read_to_string(&path) {
+                Ok(t) => t,
+                Err(_) => continue,
+            };
+            if let Some((license, confidence)) = classify_text(&text) {
+                let file = path.strip_prefix(root).unwrap_or(&path);
+                found.push(Match {
+                    license,
+                    file: file.to_string_lossy().to_string(),
+                    confidence,
+                });
+            }
+        }
+    }
+}
+
+// every license file of the repository that matches a bundled license
+pub fn classify_tree(root: &Path) -> Vec<Match> {
+    let mut found = Vec::new();
+    walk(root, root, 0, &mut found);
+    found
+}
+
+// license of the repository: the most confident match, preferring files
+// closer to the root; a GPL text next to the LGPL of the same version is
+// part of the LGPL, so the LGPL is reported
+pub fn detect(root: &Path) -> Option<Match> {
+    let found = classify_tree(root);
+    let lesser = |m: &Match| {
+        m.license
+            .strip_prefix("GPL-")
+            .map(|v| {
+                found.iter().any(|o| {
+                    o.license.strip_prefix("LGPL-") == Some(v)
+                        || (v == "2.0" && o.license == "LGPL-2.1")
+                })
+            })
+            .unwrap_or(false)
+    };
+    found
+        .iter()
+        .filter(|m| !lesser(m))
+        .min_by(|a, b| {
+            let depth = |m: &Match| m.file.matches(std::path::MAIN_SEPARATOR).count();
+            b.confidence
+                .total_cmp(&a.confidence)
+                .then(depth(a).cmp(&depth(b)))
+        })
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn license_text(id: &str) -> &'static str {
+        CORPUS.iter().find(|(l, _)| *l == id).unwrap().1
+    }
+
+    #[test]
+    fn exact_texts() {
+        for (license, text) in CORPUS {
+            let (found, confidence) = classify_text(text).unwrap();
+            assert_eq!(found, *license);
+            assert!(confidence > 0.99);
+        }
+    }
+
+    #[test]
+    fn text_with_copyright_and_reflow() {
+        let text = format!(
+            "MIT License\n\nCopyright (c) 2023 Some One\n\n{}",
+            license_text("MIT").replace('\n', " ")
+        );
+        assert_eq!(classify_text(&text).unwrap().0, "MIT");
+    }
+
+    #[test]
+    fn similar_licenses() {
+        assert_eq!(
+            classify_text(license_text("BSD-2-Clause")).unwrap().0,
+            "BSD-2-Clause"
+        );
+        assert_eq!(
+            classify_text(license_text("BSD-3-Clause")).unwrap().0,
+            "BSD-3-Clause"
+        );
+        assert_eq!(classify_text(license_text("ISC")).unwrap().0, "ISC");
+        assert_eq!(classify_text(license_text("0BSD")).unwrap().0, "0BSD");
+    }
+
+    #[test]
+    fn no_license() {
+        assert!(classify_text("").is_none());
+        assert!(classify_text("All rights reserved. Do not copy.").is_none());
+        // only half of the text
+        let mit = license_text("MIT");
+        assert!(classify_text(&mit[..mit.len() / 2]).is_none());
+    }
+
+    #[test]
+    fn license_file_names() {
+        for name in [
+            "LICENSE",
+            "license.md",
+            "LICENCE.txt",
+            "COPYING",
+            "COPYING.LESSER",
+            "LICENSE-MIT",
+            "UNLICENSE",
+        ] {
+            assert!(is_license_file(name), "{}", name);
+        }
+        for name in ["README.md", "license.rs", "licenses.json", "src"] {
+            assert!(!is_license_file(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn repository_tree() {
+        let root = std::env::temp_dir().join(format!("classify_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("sub/dir")).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join("README.md"), license_text("MIT")).unwrap();
+        std::fs::write(root.join(".git/LICENSE"), license_text("MIT")).unwrap();
+        std::fs::write(root.join("sub/dir/LICENSE.txt"), license_text("ISC")).unwrap();
+        std::fs::write(root.join("COPYING"), license_text("GPL-3.0")).unwrap();
+        std::fs::write(root.join("COPYING.LESSER"), license_text("LGPL-3.0")).unwrap();
+
+        let found = classify_tree(&root);
+        let licenses: Vec<&str> = found.iter().map(|m| m.license).collect();
+        assert_eq!(licenses, ["GPL-3.0", "LGPL-3.0", "ISC"]);
+
+        let detected = detect(&root).unwrap();
+        assert_eq!(detected.license, "LGPL-3.0");
+        assert_eq!(detected.file, "COPYING.LESSER");
+
+        std::fs::remove_dir_all(&root).unwrap();
+        assert!(detect(&root).is_none());
+    }
+}
@@ -0,0 +1,456 @@
+// license compatibility of a package's dependencies, walked transitively
+use crate::config;
+use crate::license::{Decision, LicensePolicy};
+use log::{debug, info};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+// package registry dependencies come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Ecosystem {
+    Npm,
+    Crates,
+}
+
+// dependency declared in a manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub name: String,
+    pub requirement: String,
+}
+
+// dependency whose license the policy doesn't allow
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Conflict {
+    // from the scored package down to the offending dependency
+    pub path: Vec<String>,
+    pub license: Option<String>,
+    pub decision: Decision,
+    pub rule: String,
+}
+
+// license and dependencies of a published package
+pub type Package = (Option<String>, Vec<Dependency>);
+
+pub trait Registry {
+    fn lookup(&self, ecosystem: Ecosystem, name: &str) -> Option<Package>;
+}
+
+// runtime dependencies of a package.json or npm registry version
+pub fn npm_dependencies(manifest: &Value) -> Vec<Dependency> {
+    let mut deps: Vec<Dependency> = manifest["dependencies"]
+        .as_object()
+        .map(|o| {
+            o.iter()
+                .filter_map(|(name, req)| {
+                    let req = req.as_str().unwrap_or_default();
+                    // aliases like "npm:string-width@^4.2.0" install another package
+                    match req.strip_prefix("npm:") {
+                        Some(alias) => {
+                            let (name, req) = match alias.get(1..).and_then(|a| a.rfind('@')) {
+                                Some(i) => (&alias[..i + 1], &alias[i + 2..]),
+                                None => (alias, "*"),
+                            };
+                            // malformed aliases like "npm:" name no package
+                            if name.is_empty() || name == "@" {
+                                return None;
+                            }
+                            Some(Dependency {
+                                name: name.to_string(),
+                                requirement: req.to_string(),
+                            })
+                        }
+                        None => Some(Dependency {
+                            name: name.clone(),
+                            requirement: req.to_string(),
+                        }),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    deps.sort_by(|a, b| a.name.cmp(&b.name));
+    deps
+}
+
+// normal dependencies of a Cargo.toml, including platform-specific ones
+// renamed dependencies are looked up by their package name
+pub fn cargo_dependencies(manifest: &str) -> Vec<Dependency> {
+    let table = match manifest.parse::<toml::Table>() {
+        Ok(t) => t,
+        Err(_) => return Vec::new(),
+    };
+    let mut sections: Vec<&toml::Table> = Vec::new();
+    if let Some(d) = table.get("dependencies").and_then(|d| d.as_table()) {
+        sections.push(d);
+    }
+    if let Some(targets) = table.get("target").and_then(|t| t.as_table()) {
+        for target in targets.values() {
+            if let Some(d) = target.get("dependencies").and_then(|d| d.as_table()) {
+                sections.push(d);
+            }
+        }
+    }
+
+    let mut deps = Vec::new();
+    for section in sections {
+        for (key, spec) in section {
+            let (name, requirement) = match spec {
+                toml::Value::String(v) => (key.clone(), v.clone()),
+                toml::Value::Table(t) => {
+                    // path and git dependencies are not published
+                    if t.contains_key("path") || t.contains_key("git") {
+                        continue;
+                    }
+                    let name = t.get("package").and_then(|p| p.as_str()).unwrap_or(key);
+                    let version = t.get("version").and_then(|v| v.as_str()).unwrap_or("*");
+                    (name.to_string(), version.to_string())
+                }
+                _ => continue,
+            };
+            deps.push(Dependency { name, requirement });
+        }
+    }
+    deps.sort_by(|a, b| a.name.cmp(&b.name));
+    deps.dedup_by(|a, b| a.name == b.name);
+    deps
+}
+
+// result of checking the dependencies of a package
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    // lowest LICENSE_SCORE among the dependencies, 1 without dependencies
+    pub score: f64,
+    pub checked: usize,
+    pub conflicts: Vec<Conflict>,
+    // paths to dependencies the registry failed to look up, left out of the score
+    pub unresolved: Vec<Vec<String>>,
+}
+
+// check the licenses of `deps` and, up to `depth` levels, their dependencies
+// each package is checked once, at its shortest path from the root
+pub fn check(
+    registry: &dyn Registry,
+    policy: &LicensePolicy,
+    ecosystem: Ecosystem,
+    root: &str,
+    deps: &[Dependency],
+    depth: usize,
+) -> Report {
+    let mut report = Report {
+        score: 1.0,
+        ..Default::default()
+    };
+    let mut seen: HashSet<String> = HashSet::new();
+    seen.insert(root.to_string());
+    let mut level: Vec<(Vec<String>, String)> = deps
+        .iter()
+        .map(|d| (vec![root.to_string()], d.name.clone()))
+        .collect();
+
+    // breadth first so the reported path is the shortest one
+    for _ in 0..depth {
+        let mut next = Vec::new();
+        for (parent, name) in level {
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            let mut path = parent;
+            path.push(name.clone());
+
+            // a failed lookup says nothing about the license
+            let (license, children) = match registry.lookup(ecosystem, &name) {
+                Some(p) => p,
+                None => {
+                    debug!("{}: unresolved", path.join(" > "));
+                    report.unresolved.push(path);
+                    continue;
+                }
+            };
+            report.checked += 1;
+            let names = [name.as_str()];
+            let verdict = match &license {
+                Some(l) => policy.evaluate(&names, l),
+                None => policy.evaluate(&names, "NOASSERTION"),
+            };
+            let score = policy.score(verdict.decision);
+            debug!("{}: {:?} {:?}", path.join(" > "), license, verdict.decision);
+            report.score = report.score.min(score);
+            if verdict.decision != Decision::Allow {
+                report.conflicts.push(Conflict {
+                    path: path.clone(),
+                    license,
+                    decision: verdict.decision,
+                    rule: verdict.rule,
+                });
+            }
+            next.extend(children.into_iter().map(|c| (path.clone(), c.name)));
+        }
+        level = next;
+    }
+    report
+}
+
+// npm registry and crates.io, with lookups cached for the run
+pub struct HttpRegistry {
+    client: reqwest::blocking::Client,
+    cache: std::cell::RefCell<HashMap<(Ecosystem, String), Option<Package>>>,
+}
+
+impl HttpRegistry {
+    pub fn new() -> Option<HttpRegistry> {
+        let client = reqwest::blocking::Client::builder()
+            .user_agent("ECE461_Team19_CLI")
+            .build()
+            .ok()?;
+        Some(HttpRegistry {
+            client,
+            cache: Default::default(),
+        })
+    }
+
+    fn get_json(&self, url: &str) -> Option<Value> {
+        self.client.get(url).send().ok()?.json::<Value>().ok()
+    }
+
+    // latest version of an npm package
+    fn npm(&self, name: &str) -> Option<Package> {
+        let registry = config::get().npm.registry_url.trim_end_matches('/');
+        let root = self.get_json(&format!("{}/{}", registry, name))?;
+        let latest = root["dist-tags"]["latest"].as_str()?;
+        let version = &root["versions"][latest];
+        let license = crate::metrics::npm::declared_license(version)
+            .or(crate::metrics::npm::declared_license(&root));
+        Some((license, npm_dependencies(version)))
+    }
+
+    // newest version of a crate
+    fn crate_(&self, name: &str) -> Option<Package> {
+        let api = config::get().crates.api_url.trim_end_matches('/');
+        let root = self.get_json(&format!("{}/crates/{}", api, name))?;
+        let version = root["crate"]["max_version"].as_str()?;
+        let license = root["versions"]
+            .as_array()?
+            .iter()
+            .find(|v| v["num"].as_str() == Some(version))
+            .and_then(|v| v["license"].as_str())
+            .map(|l| l.to_string());
+        let deps = self
+            .get_json(&format!("{}/crates/{}/{}/dependencies", api, name, version))
+            .and_then(|d| d["dependencies"].as_array().cloned())
+            .unwrap_or_default()
+            .iter()
+            .filter(|d| d["kind"].as_str() == Some("normal"))
+            .filter_map(|d| {
+                Some(Dependency {
+                    name: d["crate_id"].as_str()?.to_string(),
+                    requirement: d["req"].as_str().unwrap_or("*").to_string(),
+                })
+            })
+            .collect();
+        Some((license, deps))
+    }
+}
+
+impl Registry for HttpRegistry {
+    fn lookup(&self, ecosystem: Ecosystem, name: &str) -> Option<Package> {
+        let key = (ecosystem, name.to_string());
+        if let Some(cached) = self.cache.borrow().get(&key) {
+            return cached.clone();
+        }
+        info!("looking up license of {}", name);
+        let found = match ecosystem {
+            Ecosystem::Npm => self.npm(name),
+            Ecosystem::Crates => self.crate_(name),
+        };
+        self.cache.borrow_mut().insert(key, found.clone());
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // registry with packages given as (name, license, dependencies)
+    struct FakeRegistry(Vec<(&'static str, Option<&'static str>, Vec<&'static str>)>);
+
+    impl Registry for FakeRegistry {
+        fn lookup(&self, _: Ecosystem, name: &str) -> Option<Package> {
+            let (_, license, deps) = self.0.iter().find(|(n, _, _)| *n == name)?;
+            let deps = deps
+                .iter()
+                .map(|d| Dependency {
+                    name: d.to_string(),
+                    requirement: "*".to_string(),
+                })
+                .collect();
+            Some((license.map(|l| l.to_string()), deps))
+        }
+    }
+
+    fn deps(names: &[&str]) -> Vec<Dependency> {
+        names
+            .iter()
+            .map(|n| Dependency {
+                name: n.to_string(),
+                requirement: "*".to_string(),
+            })
+            .collect()
+    }
+
+    fn registry() -> FakeRegistry {
+        FakeRegistry(vec![
+            ("a", Some("MIT"), vec!["b", "c"]),
+            ("b", Some("ISC OR MIT"), vec!["gpl"]),
+            ("c", Some("BSD-3-Clause"), vec!["a"]),
+            ("gpl", Some("GPL-3.0-only"), vec![]),
+            ("unknown", None, vec![]),
+        ])
+    }
+
+    #[test]
+    fn npm_manifest() {
+        let m = serde_json::json!({
+            "dependencies": {
+                "b": "^1.0.0",
+                "a": "~2.1.0",
+                "width": "npm:string-width@^4.2.0",
+                "scoped": "npm:@types/node@18",
+                "bare": "npm:left-pad",
+                "empty": "npm:",
+                "at": "npm:@"
+            },
+            "devDependencies": { "mocha": "*" }
+        });
+        let d = npm_dependencies(&m);
+        let names: Vec<(&str, &str)> = d
+            .iter()
+            .map(|d| (d.name.as_str(), d.requirement.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("@types/node", "18"),
+                ("a", "~2.1.0"),
+                ("b", "^1.0.0"),
+                ("left-pad", "*"),
+                ("string-width", "^4.2.0"),
+            ]
+        );
+        assert!(npm_dependencies(&serde_json::json!({})).is_empty());
+    }
+
+    #[test]
+    fn cargo_manifest() {
+        let d = cargo_dependencies(
+            "[package]\n\
+             name = \"x\"\n\
+             [dependencies]\n\
+             serde = \"1.0\"\n\
+             json = { package = \"serde_json\", version = \"1\" }\n\
+             local = { path = \"../local\" }\n\
+             [dev-dependencies]\n\
+             assert_cmd = \"2\"\n\
+             [target.'cfg(unix)'.dependencies]\n\
+             libc = \"0.2\"\n",
+        );
+        let names: Vec<&str> = d.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["libc", "serde", "serde_json"]);
+        assert!(cargo_dependencies("not toml [").is_empty());
+    }
+
+    #[test]
+    fn direct_dependencies_only() {
+        let r = check(
+            &registry(),
+            &LicensePolicy::default(),
+            Ecosystem::Npm,
+            "root",
+            &deps(&["a", "b"]),
+            1,
+        );
+        assert_eq!(r.checked, 2);
+        assert_eq!(r.score, 1.0);
+        assert!(r.conflicts.is_empty());
+    }
+
+    #[test]
+    fn transitive_conflict() {
+        let r = check(
+            &registry(),
+            &LicensePolicy::default(),
+            Ecosystem::Npm,
+            "root",
+            &deps(&["a"]),
+            3,
+        );
+        // a, b, c, gpl; the cycle back to a is not followed
+        assert_eq!(r.checked, 4);
+        assert_eq!(r.score, 0.0);
+        assert_eq!(r.conflicts.len(), 1);
+        assert_eq!(r.conflicts[0].path, ["root", "a", "b", "gpl"]);
+        assert_eq!(r.conflicts[0].decision, Decision::Deny);
+        assert_eq!(r.conflicts[0].license.as_deref(), Some("GPL-3.0-only"));
+    }
+
+    #[test]
+    fn missing_license() {
+        let policy = LicensePolicy {
+            unknown: Decision::Review,
+            ..Default::default()
+        };
+        let r = check(
+            &registry(),
+            &policy,
+            Ecosystem::Npm,
+            "root",
+            &deps(&["unknown", "nowhere"]),
+            1,
+        );
+        assert_eq!(r.score, 0.5);
+        assert_eq!(r.checked, 1);
+        assert_eq!(r.conflicts.len(), 1);
+        assert!(r.conflicts[0].license.is_none());
+        // a failed lookup is no conflict
+        assert_eq!(r.unresolved, [["root", "nowhere"]]);
+    }
+
+    #[test]
+    fn unresolved_dependencies() {
+        let r = check(
+            &registry(),
+            &LicensePolicy::default(),
+            Ecosystem::Npm,
+            "root",
+            &deps(&["a", "nowhere"]),
+            1,
+        );
+        assert_eq!(r.score, 1.0);
+        assert_eq!(r.checked, 1);
+        assert!(r.conflicts.is_empty());
+        assert_eq!(r.unresolved.len(), 1);
+    }
+
+    #[test]
+    fn exceptions_apply_to_dependencies() {
+        let mut policy = LicensePolicy::default();
+        policy.exceptions.push(crate::license::Exception {
+            package: "gpl".to_string(),
+            license: None,
+            decision: Decision::Allow,
+            reason: None,
+        });
+        let r = check(
+            &registry(),
+            &policy,
+            Ecosystem::Npm,
+            "root",
+            &deps(&["b"]),
+            2,
+        );
+        assert_eq!(r.score, 1.0);
+        assert!(r.conflicts.is_empty());
+    }
+}
@@ -0,0 +1,261 @@
+// parser of SPDX license expressions
+// see: https://spdx.github.io/spdx-spec/v2.3/SPDX-license-expressions/
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    // license id, "+" for "or any later version", optional WITH exception
+    License {
+        id: String,
+        plus: bool,
+        exception: Option<String>,
+    },
+    // every term applies
+    And(Vec<Expr>),
+    // any one term may be chosen
+    Or(Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    With,
+    Id(String),
+}
+
+fn tokenize(s: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let spaced = s.replace('(', " ( ").replace(')', " ) ");
+    for word in spaced.split_whitespace() {
+        // operators are matched case-insensitively since package metadata
+        // often has them in lower case
+        let token = match word {
+            "(" => Token::Open,
+            ")" => Token::Close,
+            w if w.eq_ignore_ascii_case("and") => Token::And,
+            w if w.eq_ignore_ascii_case("or") => Token::Or,
+            w if w.eq_ignore_ascii_case("with") => Token::With,
+            w => Token::Id(w.to_string()),
+        };
+        tokens.push(token);
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    // or-expression := and-expression ("OR" and-expression)*
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut terms = vec![self.and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            terms.push(self.and()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.pop().unwrap()
+        } else {
+            Expr::Or(terms)
+        })
+    }
+
+    // and-expression := term ("AND" term)*
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut terms = vec![self.term()?];
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            terms.push(self.term()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.pop().unwrap()
+        } else {
+            Expr::And(terms)
+        })
+    }
+
+    // term := "(" or-expression ")" | license-id ["+"] ["WITH" exception-id]
+    fn term(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Open) => {
+                let e = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(e),
+                    _ => Err("missing closing parenthesis".to_string()),
+                }
+            }
+            Some(Token::Id(id)) => {
+                let (id, plus) = match id.strip_suffix('+') {
+                    Some(base) if !base.is_empty() => (base.to_string(), true),
+                    _ => (id, false),
+                };
+                let exception = if self.peek() == Some(&Token::With) {
+                    self.next();
+                    match self.next() {
+                        Some(Token::Id(e)) => Some(e),
+                        _ => return Err(format!("missing exception after {} WITH", id)),
+                    }
+                } else {
+                    None
+                };
+                Ok(Expr::License {
+                    id,
+                    plus,
+                    exception,
+                })
+            }
+            Some(t) => Err(format!("unexpected {:?}", t)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+}
+
+pub fn parse(s: &str) -> Result<Expr, String> {
+    let mut p = Parser {
+        tokens: tokenize(s),
+        pos: 0,
+    };
+    let e = p.or()?;
+    match p.next() {
+        None => Ok(e),
+        Some(t) => Err(format!("unexpected {:?} in {}", t, s)),
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // nested compound expressions are put in parentheses
+        let join = |f: &mut fmt::Formatter, terms: &[Expr], op: &str| {
+            for (i, t) in terms.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {} ", op)?;
+                }
+                match t {
+                    Expr::License { .. } => write!(f, "{}", t)?,
+                    _ => write!(f, "({})", t)?,
+                }
+            }
+            Ok(())
+        };
+        match self {
+            Expr::License {
+                id,
+                plus,
+                exception,
+            } => {
+                write!(f, "{}", id)?;
+                if *plus {
+                    write!(f, "+")?;
+                }
+                if let Some(e) = exception {
+                    write!(f, " WITH {}", e)?;
+                }
+                Ok(())
+            }
+            Expr::And(terms) => join(f, terms, "AND"),
+            Expr::Or(terms) => join(f, terms, "OR"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn license(id: &str) -> Expr {
+        Expr::License {
+            id: id.to_string(),
+            plus: false,
+            exception: None,
+        }
+    }
+
+    #[test]
+    fn single_license() {
+        assert_eq!(parse("MIT").unwrap(), license("MIT"));
+        assert_eq!(
+            parse("GPL-2.0+").unwrap(),
+            Expr::License {
+                id: "GPL-2.0".to_string(),
+                plus: true,
+                exception: None
+            }
+        );
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(
+            parse("MIT OR Apache-2.0").unwrap(),
+            Expr::Or(vec![license("MIT"), license("Apache-2.0")])
+        );
+        assert_eq!(
+            parse("(BSD-3-Clause AND MIT)").unwrap(),
+            Expr::And(vec![license("BSD-3-Clause"), license("MIT")])
+        );
+        assert_eq!(
+            parse("GPL-2.0-or-later WITH Classpath-exception-2.0").unwrap(),
+            Expr::License {
+                id: "GPL-2.0-or-later".to_string(),
+                plus: false,
+                exception: Some("Classpath-exception-2.0".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn precedence() {
+        // AND binds tighter than OR
+        assert_eq!(
+            parse("MIT or ISC and BSD-2-Clause").unwrap(),
+            Expr::Or(vec![
+                license("MIT"),
+                Expr::And(vec![license("ISC"), license("BSD-2-Clause")])
+            ])
+        );
+        assert_eq!(
+            parse("(MIT OR ISC) AND BSD-2-Clause").unwrap(),
+            Expr::And(vec![
+                Expr::Or(vec![license("MIT"), license("ISC")]),
+                license("BSD-2-Clause")
+            ])
+        );
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for s in [
+            "MIT",
+            "MIT OR Apache-2.0",
+            "(MIT OR ISC) AND BSD-2-Clause",
+            "GPL-2.0+ WITH Classpath-exception-2.0 OR MIT",
+        ] {
+            assert_eq!(parse(s).unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn bad_expressions() {
+        assert!(parse("").is_err());
+        assert!(parse("MIT OR").is_err());
+        assert!(parse("(MIT").is_err());
+        assert!(parse("MIT)").is_err());
+        assert!(parse("MIT WITH").is_err());
+        assert!(parse("AND MIT").is_err());
+    }
+}
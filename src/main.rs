@@ -1,43 +1,120 @@
+mod config;
 mod file_parser;
+mod license;
 mod metrics;
+mod output;
 
 use clap::{Parser, Subcommand};
+use config::{Config, Profile};
 use log::{debug, info, LevelFilter};
 use metrics::github::Github;
 use metrics::npm::Npm;
+use metrics::plugins::{self, Plugin};
 use metrics::Metrics;
-use std::io::Write;
-use std::{
-    collections::HashMap,
-    io::{BufRead, BufReader},
-};
+use output::{Format, NetScore};
+use std::io::{BufRead, BufReader, IsTerminal};
 
 // command line argumand parser
 #[derive(Parser)]
 struct Cli {
+    /// Configuration file, read after the system, user and project files
+    #[arg(long, global = true)]
+    config: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
 #[derive(Subcommand)]
 enum Commands {
     /// Print modules in order of trustworthiness
-    Url { url_file: String },
+    Url {
+        url_file: String,
+
+        /// Output format [default: ndjson]
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+
+        /// Include raw inputs, formula and value of every metric
+        #[arg(long)]
+        explain: bool,
+
+        /// Scoring profile used to weigh the net score [default: default]
+        #[arg(long)]
+        profile: Option<String>,
+
+        /// Number of modules scored at the same time [default: 1]
+        #[arg(long)]
+        concurrency: Option<usize>,
+
+        /// License policy file deciding LICENSE_SCORE
+        #[arg(long)]
+        license_policy: Option<String>,
+
+        /// Levels of dependencies whose licenses are checked [default: 0]
+        #[arg(long)]
+        license_depth: Option<usize>,
+    },
 
     /// Parse results of tests
     Report {
         test_result: String,
         line_analysis: String,
     },
+
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the effective configuration
+    Show,
 }
 
 fn main() -> Result<(), String> {
+    // parse command line arguments
+    let cli = Cli::parse();
+
+    // flags take precedence over files and environment variables
+    let mut config = Config::load(cli.config.as_deref())?;
+    if let Commands::Url {
+        format,
+        profile,
+        concurrency,
+        license_policy,
+        license_depth,
+        ..
+    } = &cli.command
+    {
+        if let Some(f) = format {
+            config.output.format = *f;
+        }
+        if let Some(p) = profile {
+            config.output.profile = p.clone();
+        }
+        if let Some(c) = concurrency {
+            if *c == 0 {
+                return Err("concurrency must be at least 1".to_string());
+            }
+            config.concurrency = *c;
+        }
+        if let Some(p) = license_policy {
+            config.license_policy = Some(p.clone());
+            config.load_license_policy()?;
+        }
+        if let Some(d) = license_depth {
+            config.license_depth = *d;
+        }
+    }
+    config::init(config);
+    let config = config::get();
+
     // set logging level
-    let level = std::env::var("LOG_LEVEL")
-        .ok()
-        .and_then(|i| i.parse::<u8>().ok());
-    let mut filter = match level {
-        Some(2) => LevelFilter::Debug,
-        Some(1) => LevelFilter::Info,
+    let mut filter = match config.log.level {
+        2 => LevelFilter::Debug,
+        1 => LevelFilter::Info,
         _ => LevelFilter::Off,
     };
 
@@ -45,15 +122,18 @@ fn main() -> Result<(), String> {
     let log_output = if filter == LevelFilter::Off {
         env_logger::fmt::Target::Stderr // can be anything
     } else {
-        let fp = std::env::var("LOG_FILE")
-            .ok()
+        let fp = config
+            .log
+            .file
+            .as_ref()
             .and_then(|i| std::fs::File::create(i).ok());
-        if fp.is_none() {
-            // turn off loggin gif log file not found
-            filter = LevelFilter::Off;
-            env_logger::fmt::Target::Stderr // can be anything
-        } else {
-            env_logger::fmt::Target::Pipe(Box::new(fp.unwrap()))
+        match fp {
+            Some(fp) => env_logger::fmt::Target::Pipe(Box::new(fp)),
+            None => {
+                // turn off logging if log file not found
+                filter = LevelFilter::Off;
+                env_logger::fmt::Target::Stderr // can be anything
+            }
         }
     };
 
@@ -66,10 +146,18 @@ fn main() -> Result<(), String> {
     info!("print info");
     debug!("print debug");
 
-    // parse command line arguments
-    let cli = Cli::parse();
     match &cli.command {
-        Commands::Url { url_file: f } => calcscore(f)?, //println!("url: {:?}", f),
+        Commands::Url {
+            url_file: f,
+            explain,
+            ..
+        } => {
+            let profile = config.profile(&config.output.profile)?;
+            calcscore(f, config.output.format, *explain, &profile)?
+        }
+        Commands::Config {
+            action: ConfigAction::Show,
+        } => print!("{}", config.show()?),
         Commands::Report {
             test_result: t,
             line_analysis: l,
@@ -89,127 +177,113 @@ fn main() -> Result<(), String> {
     Ok(())
 }
 
-fn calcscore(f: &String) -> Result<(), String> {
-    let mut net_scores = Vec::new();
+// module to score, checked before any metric is calculated
+enum Job {
+    Github(String),
+    Npm(String),
+}
+
+impl Job {
+    fn url(&self) -> &str {
+        match self {
+            Job::Github(u) | Job::Npm(u) => u,
+        }
+    }
+
+    fn score(
+        &self,
+        plugins: &[Box<dyn Plugin>],
+        profile: &Profile,
+        explain: bool,
+    ) -> Result<NetScore, String> {
+        let line = self.url();
+        info!("exploring {}", line);
+        let project: Box<dyn Metrics> = match self {
+            Job::Github(_) => Box::new(
+                Github::with_url(line).ok_or(format!("Error while processing url: {}", line))?,
+            ),
+            Job::Npm(_) => Box::new(
+                Npm::with_url(line).ok_or(format!("Error while processing url: {}", line))?,
+            ),
+        };
+        // calculate score
+        Ok(NetScore::new(
+            line,
+            project.as_ref(),
+            plugins,
+            profile,
+            explain,
+        ))
+    }
+}
+
+fn calcscore(f: &String, format: Format, explain: bool, profile: &Profile) -> Result<(), String> {
+    // the html report always shows the inputs behind the scores
+    let explain = explain || format == Format::Html;
 
     let file = std::fs::File::open(f).map_err(|e| format!("{}", e))?;
     let reader = BufReader::new(file);
 
+    let mut jobs = Vec::new();
     for line in reader.lines() {
         let line = line.unwrap();
         if line.is_empty() {
             continue;
         }
-        info!("exploring {}", line);
 
         // if type is github or npm
         if let Some(domain) = reqwest::Url::parse(&line)
             .map_err(|_| format!("{} is not a url", line))?
             .domain()
         {
-            let project: Box<dyn Metrics>;
             // if github
             if domain == "github.com" {
-                project = Box::new(
-                    Github::with_url(&line)
-                        .ok_or(format!("Error while processing url: {}", &line))?,
-                );
+                jobs.push(Job::Github(line));
             } else if domain == "www.npmjs.com" {
-                project = Box::new(
-                    Npm::with_url(&line).ok_or(format!("Error while processing url: {}", &line))?,
-                );
-            } else {
-                continue;
+                jobs.push(Job::Npm(line));
             }
-            // calculate score
-            info!("calculating score");
-            let mut net_score = HashMap::new();
-            let ramp_up: f64 = project.ramp_up_time();
-            let correctness: f64 = project.correctness();
-            let bus_factor: f64 = project.bus_factor();
-            let responsiveness: f64 = project.responsiveness();
-            let compatibility: f64 = project.compatibility();
-            let score: f64 = ramp_up * 0.05
-                + correctness * 0.1
-                + bus_factor * 0.1
-                + responsiveness * 0.25
-                + compatibility * 0.5;
-            net_score.insert("URL", line);
-            net_score.insert("NET_SCORE", score.to_string());
-            net_score.insert("RAMP_UP_SCORE", ramp_up.to_string());
-            net_score.insert("CORRECTNESS_SCORE", correctness.to_string());
-            net_score.insert("BUS_FACTOR_SCORE", bus_factor.to_string());
-            net_score.insert("RESPONSIVE_MAINTAINER_SCORE", responsiveness.to_string());
-            net_score.insert("LICENSE_SCORE", compatibility.to_string());
-            net_scores.push(net_score);
-        } else {
-            continue;
         }
     }
+
+    // custom metrics, shared by every module
+    let plugins = plugins::registry(&config::get().plugins);
+
+    // score modules on up to `concurrency` threads, keeping the input order
+    let results = {
+        let next = std::sync::atomic::AtomicUsize::new(0);
+        let results = std::sync::Mutex::new(Vec::new());
+        let threads = config::get().concurrency.min(jobs.len()).max(1);
+        std::thread::scope(|s| {
+            for _ in 0..threads {
+                s.spawn(|| loop {
+                    let i = next.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    let job = match jobs.get(i) {
+                        Some(job) => job,
+                        None => break,
+                    };
+                    let result = job.score(&plugins, profile, explain);
+                    results.lock().unwrap().push((i, result));
+                });
+            }
+        });
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(i, _)| *i);
+        results
+    };
+    let mut net_scores = Vec::new();
+    for (_, result) in results {
+        net_scores.push(result?);
+    }
+
     // sort by net scores
     info!("sorting by net scores");
-    net_scores.sort_by(|a, b| {
-        b["NET_SCORE"]
-            .parse::<f64>()
-            .unwrap()
-            .partial_cmp(&a["NET_SCORE"].parse::<f64>().unwrap())
-            .unwrap()
-    });
-
-    // stdout the output
+    output::sort(&mut net_scores);
+
+    // stdout the output, colored only when it is a terminal
     let stdout = std::io::stdout();
+    let color = stdout.is_terminal();
     let mut handle = stdout.lock();
 
     info!("generating output");
-    for dict in net_scores {
-        handle
-            .write_fmt(format_args!("{{\"URL\":{:?}, ", dict.get("URL").unwrap()))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"NET_SCORE\":{:.2}, ",
-                dict.get("NET_SCORE").unwrap().parse::<f64>().unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"RAMP_UP_SCORE\":{:.2}, ",
-                dict.get("RAMP_UP_SCORE").unwrap().parse::<f64>().unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"CORRECTNESS_SCORE\":{:.2}, ",
-                dict.get("CORRECTNESS_SCORE")
-                    .unwrap()
-                    .parse::<f64>()
-                    .unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"BUS_FACTOR_SCORE\":{:.2}, ",
-                dict.get("BUS_FACTOR_SCORE")
-                    .unwrap()
-                    .parse::<f64>()
-                    .unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"RESPONSIVE_MAINTAINER_SCORE\":{:.2}, ",
-                dict.get("RESPONSIVE_MAINTAINER_SCORE")
-                    .unwrap()
-                    .parse::<f64>()
-                    .unwrap()
-            ))
-            .unwrap();
-        handle
-            .write_fmt(format_args!(
-                "\"LICENSE_SCORE\":{}}}\n",
-                dict.get("LICENSE_SCORE").unwrap().parse::<f64>().unwrap()
-            ))
-            .unwrap();
-    }
-    Ok(())
+    output::write(&mut handle, &net_scores, format, color).map_err(|e| format!("{}", e))
 }
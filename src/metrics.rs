@@ -1,7 +1,57 @@
+pub mod advisories;
+pub mod bots;
+pub mod bugs;
+pub mod codebase;
+pub mod community;
+pub mod files;
 pub mod github;
+pub mod history;
 pub mod npm;
+pub mod pinning;
+pub mod plugins;
+pub mod popularity;
+pub mod releases;
+pub mod response;
+pub mod review;
+pub mod security;
+pub mod testing;
 
+use crate::license::deps::Conflict;
+use crate::license::Verdict;
+use crate::metrics::codebase::Codebase;
+use crate::metrics::plugins::Target;
+use crate::metrics::security::Check;
+use serde::Serialize;
 use statrs::distribution::{Continuous, Normal};
+use std::collections::BTreeMap;
+
+// raw observations a metric was calculated from, keyed by name
+pub type Inputs = serde_json::Map<String, serde_json::Value>;
+
+// how a metric arrived at its value
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Explanation {
+    pub inputs: Inputs,
+    pub formula: String,
+    pub value: f64,
+}
+
+impl Explanation {
+    // inputs are given as a json object, e.g. json!({"readme_lines": 120})
+    pub fn new(inputs: serde_json::Value, formula: &str, value: f64) -> Explanation {
+        Explanation {
+            inputs: match inputs {
+                serde_json::Value::Object(m) => m,
+                _ => Inputs::new(),
+            },
+            formula: formula.to_string(),
+            value,
+        }
+    }
+}
+
+// explanations keyed by output name of the metric, e.g. "RAMP_UP_SCORE"
+pub type Explain = BTreeMap<String, Explanation>;
 
 pub trait Metrics {
     // calculate metrics of module
@@ -22,6 +72,65 @@ pub trait Metrics {
     // whether module is compatible with LGPLv2.1
     fn compatibility(&self) -> f64;
 
+    // whether module has tests that run in CI
+    fn testing(&self) -> f64;
+
+    // share of dependencies pinned to a major.minor version
+    fn pinning(&self) -> f64;
+
+    // share of recently added code that went through an approved pull request
+    fn review(&self) -> f64;
+
+    // whether module still gets commits and releases
+    fn maintenance(&self) -> f64;
+
+    // known vulnerabilities of the evaluated version
+    fn vulnerabilities(&self) -> f64;
+
+    // share of the security checks passed
+    fn security(&self) -> f64;
+
+    // whether module has what contributors need: guidelines, templates, governance
+    fn community(&self) -> f64;
+
+    // how widely module is used
+    fn popularity(&self) -> f64;
+
+    // explanations recorded by the metrics calculated so far
+    fn explain(&self) -> Explain {
+        Explain::new()
+    }
+
+    // web page of the repository the metrics were calculated from
+    fn repository_url(&self) -> Option<String> {
+        None
+    }
+
+    // outcome of each security check, once `security` was calculated
+    fn security_checks(&self) -> Vec<Check> {
+        Vec::new()
+    }
+
+    // whether each community item was found, once `community` was calculated
+    fn community_items(&self) -> BTreeMap<String, bool> {
+        BTreeMap::new()
+    }
+
+    // dependencies found by the license check whose licenses the policy doesn't allow
+    fn license_conflicts(&self) -> Vec<Conflict> {
+        Vec::new()
+    }
+
+    // size, languages and complexity of the cloned repository
+    fn codebase(&self) -> Option<Codebase> {
+        None
+    }
+
+    // what plugins are told about the module, besides its url
+    fn target(&self) -> Target {
+        Target::default()
+    }
+
     // formulas for calculating metrics
     fn calc_ramp_up_time(lines: u32) -> f64
     where
@@ -34,6 +143,16 @@ pub trait Metrics {
         normal.pdf(x) * x.sqrt() / 0.2613
     }
 
+    // ease of reading the code: source lines weigh 0.5, halving every 50,000
+    // lines, and branches per 100 lines 0.5, halving every 20
+    fn calc_codebase_ramp_up(source_lines: usize, decision_density: f64) -> f64
+    where
+        Self: Sized,
+    {
+        0.5 * 0.5_f64.powf(source_lines as f64 / 50_000.0)
+            + 0.5 * 0.5_f64.powf(decision_density.max(0.0) / 20.0)
+    }
+
     fn calc_correctness(all: u32, closed: u32) -> f64
     where
         Self: Sized,
@@ -45,30 +164,311 @@ pub trait Metrics {
         }
     }
 
-    fn calc_compatibility(license: &str) -> f64
+    // mean of log10(1 + count) / log10(1 + reference) over (count, reference)
+    // signals, each capped at 1; 0 without signals
+    fn calc_popularity(signals: &[(f64, f64)]) -> f64
+    where
+        Self: Sized,
+    {
+        if signals.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = signals
+            .iter()
+            .map(|(count, reference)| {
+                ((1.0 + count.max(0.0)).log10() / (1.0 + reference).log10()).min(1.0)
+            })
+            .sum();
+        sum / signals.len() as f64
+    }
+
+    // community files found per item looked for, weighing 0.7 next to
+    // GitHub's health percentage when available
+    fn calc_community(found: usize, items: usize, health_percentage: Option<f64>) -> f64
+    where
+        Self: Sized,
+    {
+        let files = if items == 0 {
+            0.0
+        } else {
+            found.min(items) as f64 / items as f64
+        };
+        match health_percentage {
+            Some(h) => 0.7 * files + 0.3 * (h / 100.0).clamp(0.0, 1.0),
+            None => files,
+        }
+    }
+
+    // passed checks per decided check, 0 if none could be decided
+    fn calc_security(checks: &[Option<bool>]) -> f64
+    where
+        Self: Sized,
+    {
+        let decided: Vec<bool> = checks.iter().flatten().cloned().collect();
+        if decided.is_empty() {
+            0.0
+        } else {
+            decided.iter().filter(|p| **p).count() as f64 / decided.len() as f64
+        }
+    }
+
+    // 1 without unpatched advisories, 0 with a critical one,
+    // else 1 less 0.5 per high, 0.25 per moderate or unknown and 0.1 per low
+    fn calc_vulnerabilities(severities: &[&str]) -> f64
+    where
+        Self: Sized,
+    {
+        let mut score = 1.0;
+        for s in severities {
+            score -= match s.to_uppercase().as_str() {
+                "CRITICAL" => 1.0,
+                "HIGH" => 0.5,
+                "LOW" => 0.1,
+                _ => 0.25,
+            };
+        }
+        f64::max(score, 0.0)
+    }
+
+    // 0 when archived, else recent commits weigh 0.4, halving every 180 days,
+    // the last release 0.3, halving every year, and releases in two years 0.3,
+    // full at eight (one a quarter)
+    fn calc_maintenance(
+        archived: bool,
+        days_since_commit: Option<f64>,
+        days_since_release: Option<f64>,
+        releases_last_2_years: usize,
+    ) -> f64
+    where
+        Self: Sized,
+    {
+        if archived {
+            return 0.0;
+        }
+        let decay = |days: Option<f64>, half_life: f64| match days {
+            Some(d) => 0.5_f64.powf(d.max(0.0) / half_life),
+            None => 0.0,
+        };
+        0.4 * decay(days_since_commit, 180.0)
+            + 0.3 * decay(days_since_release, 365.0)
+            + 0.3 * (releases_last_2_years as f64 / 8.0).min(1.0)
+    }
+
+    // reviewed lines per added line, 0 without additions
+    fn calc_review(additions: u64, reviewed: u64) -> f64
+    where
+        Self: Sized,
+    {
+        if additions == 0 {
+            0.0
+        } else {
+            reviewed.min(additions) as f64 / additions as f64
+        }
+    }
+
+    // pinned dependencies per dependency, 1 for a manifest without dependencies
+    // and 0 when no manifest could be read (`total` is None)
+    fn calc_pinning(total: Option<usize>, pinned: usize) -> f64
+    where
+        Self: Sized,
+    {
+        match total {
+            None => 0.0,
+            Some(0) => 1.0,
+            Some(t) => pinned.min(t) as f64 / t as f64,
+        }
+    }
+
+    // tests count up to half, by test lines per source line up to 0.3,
+    // CI configuration 0.3 and its last run 0.2, half of that when unknown
+    fn calc_testing(test_files: usize, ratio: f64, ci: bool, ci_passed: Option<bool>) -> f64
     where
         Self: Sized,
     {
-        let acceptable = [
-            "LGPL-2.1-only",
-            "LGPL-2.1",
-            "LGPL-2.1-or-later",
-            "LGPL-3.0-only",
-            "LGPL-3.0",
-            "BSD-3-Clause",
-            "MIT",
-            "X11",
-            "CC0-1.0",
-            "Unlicense",
-        ];
-        if acceptable.contains(&license) {
+        let tests = if test_files == 0 {
+            0.0
+        } else {
+            0.5 * (ratio / 0.3).clamp(0.0, 1.0)
+        };
+        let ci = match (ci, ci_passed) {
+            (false, _) => 0.0,
+            (true, Some(true)) => 0.5,
+            (true, Some(false)) => 0.3,
+            (true, None) => 0.4,
+        };
+        tests + ci
+    }
+
+    // bugs reported in the window that were fixed, not counting those closed
+    // as not planned, and how long open bugs have waited, halving every 180 days
+    fn calc_bug_correctness(
+        reported: u64,
+        completed: u64,
+        not_planned: u64,
+        open_median_age_days: Option<f64>,
+    ) -> f64
+    where
+        Self: Sized,
+    {
+        let fixable = reported.saturating_sub(not_planned);
+        let fixed = if fixable == 0 {
             1.0
         } else {
+            (completed as f64 / fixable as f64).min(1.0)
+        };
+        let age = match open_median_age_days {
+            Some(d) => 0.5_f64.powf(d.max(0.0) / 180.0),
+            None => 1.0,
+        };
+        0.7 * fixed + 0.3 * age
+    }
+
+    // median days to a maintainer's response and to closing, halving the
+    // score every 7 and 30 days respectively; 0 for what couldn't be measured
+    fn calc_responsiveness(response_days: Option<f64>, close_days: Option<f64>) -> f64
+    where
+        Self: Sized,
+    {
+        let decay = |days: Option<f64>, half_life: f64| match days {
+            Some(d) => 0.5_f64.powf(d.max(0.0) / half_life),
+            None => 0.0,
+        };
+        0.5 * decay(response_days, 7.0) + 0.5 * decay(close_days, 30.0)
+    }
+
+    // truck factor, or people involved when it's unknown, mapped to [0, 1)
+    fn calc_bus_factor(people: u32) -> f64
+    where
+        Self: Sized,
+    {
+        if people == 0 {
             0.0
+        } else {
+            (2.0 * people as f64) / (people as f64 + 1.0) - 1.0
         }
     }
+
+    // decide on the license of a package with the configured license policy
+    // `packages` are the names the package is known by, for exceptions
+    fn calc_compatibility(packages: &[&str], license: &str) -> (f64, Verdict)
+    where
+        Self: Sized,
+    {
+        let policy = &crate::config::get().license;
+        let verdict = policy.evaluate(packages, license);
+        (policy.score(verdict.decision), verdict)
+    }
+}
+
+// metric of the net score
+pub struct Metric {
+    // key of its weight in scoring profiles
+    pub name: &'static str,
+    // output name of its score
+    pub column: &'static str,
+    // weight in the default profile
+    pub weight: f64,
+    // profiles must give a weight, for the metrics profiles predate
+    pub required: bool,
+    pub score: fn(&dyn Metrics) -> f64,
 }
 
+// every metric, in output order
+// a new metric is a method of `Metrics` and an entry here
+pub const METRICS: [Metric; 13] = [
+    Metric {
+        name: "ramp_up",
+        column: "RAMP_UP_SCORE",
+        weight: 0.05,
+        required: true,
+        score: |m| m.ramp_up_time(),
+    },
+    Metric {
+        name: "correctness",
+        column: "CORRECTNESS_SCORE",
+        weight: 0.1,
+        required: true,
+        score: |m| m.correctness(),
+    },
+    Metric {
+        name: "bus_factor",
+        column: "BUS_FACTOR_SCORE",
+        weight: 0.1,
+        required: true,
+        score: |m| m.bus_factor(),
+    },
+    Metric {
+        name: "responsiveness",
+        column: "RESPONSIVE_MAINTAINER_SCORE",
+        weight: 0.15,
+        required: true,
+        score: |m| m.responsiveness(),
+    },
+    Metric {
+        name: "license",
+        column: "LICENSE_SCORE",
+        weight: 0.4,
+        required: true,
+        score: |m| m.compatibility(),
+    },
+    Metric {
+        name: "testing",
+        column: "TESTING_SCORE",
+        weight: 0.0,
+        required: false,
+        score: |m| m.testing(),
+    },
+    Metric {
+        name: "pinning",
+        column: "PINNING_SCORE",
+        weight: 0.1,
+        required: false,
+        score: |m| m.pinning(),
+    },
+    Metric {
+        name: "review",
+        column: "REVIEW_SCORE",
+        weight: 0.0,
+        required: false,
+        score: |m| m.review(),
+    },
+    Metric {
+        name: "maintenance",
+        column: "MAINTENANCE_SCORE",
+        weight: 0.1,
+        required: false,
+        score: |m| m.maintenance(),
+    },
+    Metric {
+        name: "vulnerabilities",
+        column: "VULNERABILITY_SCORE",
+        weight: 0.0,
+        required: false,
+        score: |m| m.vulnerabilities(),
+    },
+    Metric {
+        name: "security",
+        column: "SECURITY_SCORE",
+        weight: 0.0,
+        required: false,
+        score: |m| m.security(),
+    },
+    Metric {
+        name: "community",
+        column: "COMMUNITY_SCORE",
+        weight: 0.0,
+        required: false,
+        score: |m| m.community(),
+    },
+    Metric {
+        name: "popularity",
+        column: "POPULARITY_SCORE",
+        weight: 0.0,
+        required: false,
+        score: |m| m.popularity(),
+    },
+];
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,6 +490,40 @@ mod tests {
         fn compatibility(&self) -> f64 {
             0.0
         }
+        fn testing(&self) -> f64 {
+            0.0
+        }
+        fn pinning(&self) -> f64 {
+            0.0
+        }
+        fn review(&self) -> f64 {
+            0.0
+        }
+        fn maintenance(&self) -> f64 {
+            0.0
+        }
+        fn vulnerabilities(&self) -> f64 {
+            0.0
+        }
+        fn security(&self) -> f64 {
+            0.0
+        }
+        fn community(&self) -> f64 {
+            0.0
+        }
+        fn popularity(&self) -> f64 {
+            0.0
+        }
+    }
+
+    #[test]
+    fn metric_table() {
+        let names: std::collections::BTreeSet<&str> = METRICS.iter().map(|m| m.name).collect();
+        let columns: std::collections::BTreeSet<&str> = METRICS.iter().map(|m| m.column).collect();
+        assert_eq!(names.len(), METRICS.len());
+        assert_eq!(columns.len(), METRICS.len());
+        assert!(METRICS.iter().all(|m| m.column.ends_with("_SCORE")));
+        assert_eq!((METRICS[0].score)(&TestMetrics), 0.0);
     }
 
     #[test]
@@ -104,6 +538,14 @@ mod tests {
         assert!(TestMetrics::calc_ramp_up_time(150) >= 0.99);
     }
 
+    #[test]
+    fn codebase_ramp_up_cases() {
+        assert!(TestMetrics::calc_codebase_ramp_up(0, 0.0) == 1.0);
+        assert!(TestMetrics::calc_codebase_ramp_up(50_000, 20.0) == 0.5);
+        assert!(TestMetrics::calc_codebase_ramp_up(0, 20.0) == 0.75);
+        assert!(TestMetrics::calc_codebase_ramp_up(usize::MAX, f64::INFINITY) == 0.0);
+    }
+
     #[test]
     fn correctness_edge_cases() {
         assert!(TestMetrics::calc_correctness(0, 0) == 0.0);
@@ -118,11 +560,117 @@ mod tests {
         assert!(TestMetrics::calc_correctness(2000, 100) == 100.0 / 2000.0);
     }
 
+    #[test]
+    fn popularity_cases() {
+        assert!(TestMetrics::calc_popularity(&[]) == 0.0);
+        assert!(TestMetrics::calc_popularity(&[(0.0, 1000.0)]) == 0.0);
+        assert!(TestMetrics::calc_popularity(&[(999.0, 999.0)]) == 1.0);
+        assert!(TestMetrics::calc_popularity(&[(1e9, 1000.0), (0.0, 10.0)]) == 0.5);
+        let half = TestMetrics::calc_popularity(&[(99.0, 9999.0)]);
+        assert!((half - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn community_cases() {
+        assert!(TestMetrics::calc_community(0, 0, None) == 0.0);
+        assert!(TestMetrics::calc_community(3, 6, None) == 0.5);
+        assert!(TestMetrics::calc_community(6, 6, Some(100.0)) == 1.0);
+        assert!(TestMetrics::calc_community(0, 6, Some(50.0)) == 0.15);
+    }
+
+    #[test]
+    fn security_cases() {
+        assert!(TestMetrics::calc_security(&[]) == 0.0);
+        assert!(TestMetrics::calc_security(&[None, None]) == 0.0);
+        assert!(TestMetrics::calc_security(&[Some(true), None, Some(false)]) == 0.5);
+        assert!(TestMetrics::calc_security(&[Some(true), Some(true)]) == 1.0);
+    }
+
+    #[test]
+    fn vulnerabilities_cases() {
+        assert!(TestMetrics::calc_vulnerabilities(&[]) == 1.0);
+        assert!(TestMetrics::calc_vulnerabilities(&["critical"]) == 0.0);
+        assert!(TestMetrics::calc_vulnerabilities(&["HIGH", "MODERATE"]) == 0.25);
+        assert!(TestMetrics::calc_vulnerabilities(&["HIGH", "HIGH", "LOW"]) == 0.0);
+        assert!(TestMetrics::calc_vulnerabilities(&["UNKNOWN"]) == 0.75);
+    }
+
+    #[test]
+    fn maintenance_cases() {
+        assert!(TestMetrics::calc_maintenance(true, Some(0.0), Some(0.0), 8) == 0.0);
+        assert!(TestMetrics::calc_maintenance(false, Some(0.0), Some(0.0), 8) == 1.0);
+        assert!(TestMetrics::calc_maintenance(false, Some(0.0), Some(0.0), 20) == 1.0);
+        assert!(TestMetrics::calc_maintenance(false, None, None, 0) == 0.0);
+        assert!(
+            TestMetrics::calc_maintenance(false, Some(180.0), Some(365.0), 4) == 0.2 + 0.15 + 0.15
+        );
+    }
+
+    #[test]
+    fn review_cases() {
+        assert!(TestMetrics::calc_review(0, 0) == 0.0);
+        assert!(TestMetrics::calc_review(200, 150) == 0.75);
+        assert!(TestMetrics::calc_review(200, 300) == 1.0);
+    }
+
+    #[test]
+    fn pinning_cases() {
+        assert!(TestMetrics::calc_pinning(Some(0), 0) == 1.0);
+        assert!(TestMetrics::calc_pinning(Some(4), 1) == 0.25);
+        assert!(TestMetrics::calc_pinning(Some(4), 4) == 1.0);
+        // no manifest could be read
+        assert!(TestMetrics::calc_pinning(None, 0) == 0.0);
+    }
+
+    #[test]
+    fn testing_cases() {
+        assert!(TestMetrics::calc_testing(0, 0.0, false, None) == 0.0);
+        assert!(TestMetrics::calc_testing(10, 0.3, true, Some(true)) == 1.0);
+        assert!(TestMetrics::calc_testing(10, 3.0, false, None) == 0.5);
+        assert!(TestMetrics::calc_testing(10, 0.15, true, Some(false)) == 0.25 + 0.3);
+        assert!(TestMetrics::calc_testing(0, 0.0, true, None) == 0.4);
+    }
+
+    #[test]
+    fn bug_correctness_cases() {
+        assert!(TestMetrics::calc_bug_correctness(0, 0, 0, None) == 1.0);
+        assert!(TestMetrics::calc_bug_correctness(10, 10, 0, None) == 1.0);
+        // not planned issues are not counted against the project
+        assert!(TestMetrics::calc_bug_correctness(10, 5, 5, None) == 1.0);
+        assert!(TestMetrics::calc_bug_correctness(10, 0, 0, Some(0.0)) == 0.3);
+        assert!(TestMetrics::calc_bug_correctness(10, 5, 0, Some(180.0)) == 0.35 + 0.15);
+    }
+
+    #[test]
+    fn responsiveness_cases() {
+        assert!(TestMetrics::calc_responsiveness(None, None) == 0.0);
+        assert!(TestMetrics::calc_responsiveness(Some(0.0), Some(0.0)) == 1.0);
+        assert!(TestMetrics::calc_responsiveness(Some(7.0), Some(30.0)) == 0.5);
+        assert!(TestMetrics::calc_responsiveness(Some(0.0), None) == 0.5);
+        assert!(TestMetrics::calc_responsiveness(Some(1000.0), Some(1000.0)) < 0.01);
+    }
+
+    #[test]
+    fn bus_factor_cases() {
+        assert!(TestMetrics::calc_bus_factor(0) == 0.0);
+        assert!(TestMetrics::calc_bus_factor(1) == 0.0);
+        assert!((TestMetrics::calc_bus_factor(2) - 1.0 / 3.0).abs() < 1e-9);
+        assert!(TestMetrics::calc_bus_factor(3) == 0.5);
+        // a score above 0.5 takes a truck factor of at least 4
+        assert!((TestMetrics::calc_bus_factor(4) - 0.6).abs() < 1e-9);
+        assert!((TestMetrics::calc_bus_factor(9) - 0.8).abs() < 1e-9);
+        assert!(TestMetrics::calc_bus_factor(u32::MAX) < 1.0);
+    }
+
     #[test]
     fn compatibility_tests() {
-        assert!(TestMetrics::calc_compatibility("MIT") == 1.0);
-        assert!(TestMetrics::calc_compatibility("LGPL-2.1") == 1.0);
-        assert!(TestMetrics::calc_compatibility("notMIT") == 0.0);
-        assert!(TestMetrics::calc_compatibility("") == 0.0);
+        assert!(TestMetrics::calc_compatibility(&[], "MIT").0 == 1.0);
+        assert!(TestMetrics::calc_compatibility(&[], "LGPL-2.1").0 == 1.0);
+        assert!(TestMetrics::calc_compatibility(&[], "notMIT").0 == 0.0);
+        assert!(TestMetrics::calc_compatibility(&[], "").0 == 0.0);
+        assert_eq!(
+            TestMetrics::calc_compatibility(&["pkg"], "MIT").1.rule,
+            "allow: MIT"
+        );
     }
 }
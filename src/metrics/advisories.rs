@@ -0,0 +1,463 @@
+// known vulnerabilities from a local copy of an OSV advisory database
+// see https://ossf.github.io/osv-schema/
+use serde::Serialize;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::OnceLock;
+
+// event of an affected version range
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Introduced(String),
+    Fixed(String),
+    LastAffected(String),
+}
+
+// versions of one package an advisory applies to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Affected {
+    pub ecosystem: String,
+    pub name: String,
+    pub ranges: Vec<Vec<Event>>,
+    pub versions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Advisory {
+    pub id: String,
+    pub summary: String,
+    // seconds since the epoch
+    pub published: Option<i64>,
+    // LOW, MODERATE, HIGH, CRITICAL or UNKNOWN
+    pub severity: String,
+    pub affected: Vec<Affected>,
+}
+
+fn timestamp(v: &Value) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(v.as_str()?)
+        .ok()
+        .map(|t| t.timestamp())
+}
+
+// advisory of an OSV record, None for withdrawn or malformed ones
+pub fn parse(record: &Value) -> Option<Advisory> {
+    if !record["withdrawn"].is_null() {
+        return None;
+    }
+    let affected = record["affected"]
+        .as_array()?
+        .iter()
+        .filter_map(|a| {
+            let ranges = a["ranges"]
+                .as_array()
+                .map(|r| {
+                    r.iter()
+                        // git ranges are commits, not versions
+                        .filter(|r| r["type"].as_str() != Some("GIT"))
+                        .map(|r| {
+                            r["events"]
+                                .as_array()
+                                .map(|e| e.iter().filter_map(event).collect())
+                                .unwrap_or_default()
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            let versions = a["versions"]
+                .as_array()
+                .map(|v| {
+                    v.iter()
+                        .filter_map(|v| v.as_str().map(|v| v.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default();
+            Some(Affected {
+                ecosystem: a["package"]["ecosystem"].as_str()?.to_string(),
+                name: a["package"]["name"].as_str()?.to_string(),
+                ranges,
+                versions,
+            })
+        })
+        .collect();
+    let severity = record["database_specific"]["severity"]
+        .as_str()
+        .map(|s| s.to_uppercase())
+        .unwrap_or_else(|| "UNKNOWN".to_string());
+    Some(Advisory {
+        id: record["id"].as_str()?.to_string(),
+        summary: record["summary"].as_str().unwrap_or_default().to_string(),
+        published: timestamp(&record["published"]),
+        severity,
+        affected,
+    })
+}
+
+fn event(e: &Value) -> Option<Event> {
+    let o = e.as_object()?;
+    let (k, v) = o.iter().next()?;
+    let v = v.as_str()?.to_string();
+    match k.as_str() {
+        "introduced" => Some(Event::Introduced(v)),
+        "fixed" => Some(Event::Fixed(v)),
+        "last_affected" => Some(Event::LastAffected(v)),
+        _ => None,
+    }
+}
+
+// compare versions like 1.2.3, 1.2.3-beta.1 or 0, pre-releases before releases
+pub fn compare(a: &str, b: &str) -> Ordering {
+    let split = |v: &str| {
+        let v = v.trim_start_matches('v');
+        let v = v.split('+').next().unwrap_or_default();
+        let (release, pre) = match v.split_once('-') {
+            Some((r, p)) => (r.to_string(), Some(p.to_string())),
+            None => (v.to_string(), None),
+        };
+        let nums: Vec<u64> = release.split('.').map(|n| n.parse().unwrap_or(0)).collect();
+        (nums, pre)
+    };
+    let (an, ap) = split(a);
+    let (bn, bp) = split(b);
+    let len = an.len().max(bn.len());
+    for i in 0..len {
+        let o = an.get(i).unwrap_or(&0).cmp(bn.get(i).unwrap_or(&0));
+        if o != Ordering::Equal {
+            return o;
+        }
+    }
+    match (ap, bp) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => compare_pre_release(&a, &b),
+    }
+}
+
+// dot separated identifiers compare numerically when both are numbers, numbers
+// before other identifiers, and a shorter pre-release first when one is a
+// prefix of the other (semver section 11), e.g. beta.2 < beta.11 < beta.rc
+fn compare_pre_release(a: &str, b: &str) -> Ordering {
+    let mut a = a.split('.');
+    let mut b = b.split('.');
+    loop {
+        let o = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => match (x.parse::<u64>(), y.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => x.cmp(y),
+            },
+        };
+        if o != Ordering::Equal {
+            return o;
+        }
+    }
+}
+
+impl Affected {
+    // whether `version` is affected
+    pub fn affects(&self, version: &str) -> bool {
+        if self.versions.iter().any(|v| v == version) {
+            return true;
+        }
+        self.ranges.iter().any(|events| {
+            let mut affected = false;
+            for e in events {
+                match e {
+                    Event::Introduced(v) => {
+                        if v == "0" || compare(version, v) != Ordering::Less {
+                            affected = true;
+                        }
+                    }
+                    Event::Fixed(v) => {
+                        if compare(version, v) != Ordering::Less {
+                            affected = false;
+                        }
+                    }
+                    Event::LastAffected(v) => {
+                        if compare(version, v) == Ordering::Greater {
+                            affected = false;
+                        }
+                    }
+                }
+            }
+            affected
+        })
+    }
+
+    // versions fixing the advisory
+    pub fn fixed(&self) -> Vec<&str> {
+        self.ranges
+            .iter()
+            .flatten()
+            .filter_map(|e| match e {
+                Event::Fixed(v) => Some(v.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // whether some range has no fix, so the latest versions are affected
+    pub fn unfixed(&self) -> bool {
+        self.ranges
+            .iter()
+            .any(|r| !r.iter().any(|e| matches!(e, Event::Fixed(_))))
+            || (self.ranges.is_empty() && !self.versions.is_empty())
+    }
+}
+
+// advisories by (lowercase ecosystem, package name)
+#[derive(Debug, Default)]
+pub struct Database {
+    pub files: usize,
+    by_package: HashMap<(String, String), Vec<Advisory>>,
+}
+
+impl Database {
+    // every *.json file under `dir`, recursively; unreadable files are skipped
+    pub fn load(dir: &Path) -> Database {
+        let mut db = Database::default();
+        let mut dirs = vec![dir.to_path_buf()];
+        while let Some(d) = dirs.pop() {
+            let entries = match std::fs::read_dir(&d) {
+                Ok(e) => e,
+                Err(_) => continue,
+            };
+            for entry in entries.filter_map(|e| e.ok()) {
+                let path = entry.path();
+                // symlinked directories are skipped, they could loop back
+                let symlink = entry.file_type().is_ok_and(|t| t.is_symlink());
+                if path.is_dir() {
+                    if !symlink {
+                        dirs.push(path);
+                    }
+                } else if path.extension().is_some_and(|e| e == "json") {
+                    let record = std::fs::read_to_string(&path)
+                        .ok()
+                        .and_then(|s| serde_json::from_str::<Value>(&s).ok());
+                    if let Some(a) = record.as_ref().and_then(parse) {
+                        db.files += 1;
+                        db.insert(a);
+                    }
+                }
+            }
+        }
+        db
+    }
+
+    pub fn insert(&mut self, advisory: Advisory) {
+        let mut keys: Vec<_> = advisory
+            .affected
+            .iter()
+            .map(|a| (a.ecosystem.to_lowercase(), a.name.clone()))
+            .collect();
+        // a package can be listed in several entries, e.g. one per range
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            self.by_package
+                .entry(key)
+                .or_default()
+                .push(advisory.clone());
+        }
+    }
+
+    pub fn advisories(&self, ecosystem: &str, name: &str) -> &[Advisory] {
+        self.by_package
+            .get(&(ecosystem.to_lowercase(), name.to_string()))
+            .map(|a| a.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+// database of the configured directory, loaded once per run
+pub fn database() -> Option<&'static Database> {
+    static DB: OnceLock<Option<Database>> = OnceLock::new();
+    DB.get_or_init(|| {
+        let dir = crate::config::get().advisories.dir.as_ref()?;
+        Some(Database::load(dir))
+    })
+    .as_ref()
+}
+
+// advisory affecting the evaluated version
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Unpatched {
+    pub id: String,
+    pub severity: String,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Report {
+    // advisories of the package, in any version
+    pub advisories: usize,
+    pub unpatched: Vec<Unpatched>,
+    // days from publication of an advisory to the release of its first fix
+    pub median_days_to_fix: Option<f64>,
+}
+
+// advisories of a package affecting `version`, or without fix when the version is unknown
+// `times` are the publish times of the package's versions
+pub fn check(
+    db: &Database,
+    ecosystem: &str,
+    name: &str,
+    version: Option<&str>,
+    times: &BTreeMap<String, i64>,
+) -> Report {
+    let advisories = db.advisories(ecosystem, name);
+    let mine = |a: &Advisory| -> Vec<Affected> {
+        a.affected
+            .iter()
+            .filter(|x| x.ecosystem.eq_ignore_ascii_case(ecosystem) && x.name == name)
+            .cloned()
+            .collect()
+    };
+    let mut report = Report {
+        advisories: advisories.len(),
+        ..Default::default()
+    };
+    let mut days_to_fix = Vec::new();
+    for a in advisories {
+        let affected = mine(a);
+        let hit = match version {
+            Some(v) => affected.iter().any(|x| x.affects(v)),
+            None => affected.iter().any(|x| x.unfixed()),
+        };
+        if hit {
+            report.unpatched.push(Unpatched {
+                id: a.id.clone(),
+                severity: a.severity.clone(),
+                summary: a.summary.clone(),
+            });
+        }
+        let first_fix = affected
+            .iter()
+            .flat_map(|x| x.fixed())
+            .filter_map(|v| times.get(v))
+            .min();
+        if let (Some(fix), Some(published)) = (first_fix, a.published) {
+            days_to_fix.push((fix - published).max(0) as f64 / 86400.0);
+        }
+    }
+    report.median_days_to_fix = crate::metrics::response::percentile(&days_to_fix, 50.0);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record() -> Value {
+        json!({
+            "id": "GHSA-1",
+            "summary": "Prototype pollution",
+            "published": "2023-01-01T00:00:00Z",
+            "database_specific": { "severity": "HIGH" },
+            "affected": [{
+                "package": { "ecosystem": "npm", "name": "lib" },
+                "ranges": [{ "type": "SEMVER", "events": [
+                    { "introduced": "0" }, { "fixed": "1.2.3" },
+                    { "introduced": "2.0.0" }, { "last_affected": "2.0.5" }
+                ] }],
+                "versions": ["3.0.0-beta"]
+            }]
+        })
+    }
+
+    #[test]
+    fn versions() {
+        assert_eq!(compare("1.2.3", "1.2.3"), Ordering::Equal);
+        assert_eq!(compare("1.10.0", "1.9.9"), Ordering::Greater);
+        assert_eq!(compare("1.2.3-beta", "1.2.3"), Ordering::Less);
+        assert_eq!(compare("v1.2", "1.2.0"), Ordering::Equal);
+        assert_eq!(compare("0", "0.0.1"), Ordering::Less);
+        assert_eq!(compare("1.0.0-beta.2", "1.0.0-beta.11"), Ordering::Less);
+        assert_eq!(compare("1.0.0-beta.11", "1.0.0-beta.rc"), Ordering::Less);
+        assert_eq!(compare("1.0.0-alpha", "1.0.0-alpha.1"), Ordering::Less);
+        assert_eq!(compare("1.0.0-rc.1", "1.0.0-beta.11"), Ordering::Greater);
+    }
+
+    #[test]
+    fn affected_versions() {
+        let a = parse(&record()).unwrap();
+        assert_eq!(a.severity, "HIGH");
+        let x = &a.affected[0];
+        assert!(x.affects("1.0.0"));
+        assert!(!x.affects("1.2.3"));
+        assert!(!x.affects("1.5.0"));
+        assert!(x.affects("2.0.5"));
+        assert!(!x.affects("2.0.6"));
+        assert!(x.affects("3.0.0-beta"));
+        assert_eq!(x.fixed(), ["1.2.3"]);
+        assert!(!x.unfixed());
+
+        let mut withdrawn = record();
+        withdrawn["withdrawn"] = json!("2023-02-01T00:00:00Z");
+        assert!(parse(&withdrawn).is_none());
+    }
+
+    #[test]
+    fn check_package() {
+        let mut db = Database::default();
+        db.insert(parse(&record()).unwrap());
+        let mut open = record();
+        open["id"] = json!("GHSA-2");
+        open["database_specific"] = json!({});
+        open["affected"][0]["ranges"][0]["events"] = json!([{ "introduced": "1.0.0" }]);
+        db.insert(parse(&open).unwrap());
+
+        let times = BTreeMap::from([("1.2.3".to_string(), 1672531200 + 10 * 86400)]);
+        let r = check(&db, "NPM", "lib", Some("1.1.0"), &times);
+        assert_eq!(r.advisories, 2);
+        let ids: Vec<&str> = r.unpatched.iter().map(|u| u.id.as_str()).collect();
+        assert_eq!(ids, ["GHSA-1", "GHSA-2"]);
+        assert_eq!(r.unpatched[1].severity, "UNKNOWN");
+        assert_eq!(r.median_days_to_fix, Some(10.0));
+
+        // without a version, only advisories without fix count
+        let r = check(&db, "npm", "lib", None, &times);
+        assert_eq!(r.unpatched.len(), 1);
+        assert_eq!(r.unpatched[0].id, "GHSA-2");
+
+        assert_eq!(check(&db, "npm", "other", None, &times), Report::default());
+    }
+
+    #[test]
+    fn repeated_packages() {
+        let mut r = record();
+        let lib = r["affected"][0].clone();
+        r["affected"] = json!([
+            lib,
+            { "package": { "ecosystem": "npm", "name": "other" }, "versions": ["1.0.0"] },
+            { "package": { "ecosystem": "NPM", "name": "lib" }, "versions": ["4.0.0"] }
+        ]);
+        let mut db = Database::default();
+        db.insert(parse(&r).unwrap());
+        assert_eq!(db.advisories("npm", "lib").len(), 1);
+        assert_eq!(db.advisories("npm", "other").len(), 1);
+    }
+
+    #[test]
+    fn load_directory() {
+        let dir = std::env::temp_dir().join(format!("osv_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("npm")).unwrap();
+        std::fs::write(dir.join("npm/GHSA-1.json"), record().to_string()).unwrap();
+        std::fs::write(dir.join("npm/broken.json"), "{").unwrap();
+        std::fs::write(dir.join("README.md"), "").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&dir, dir.join("npm/loop")).unwrap();
+        let db = Database::load(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(db.files, 1);
+        assert_eq!(db.advisories("npm", "lib").len(), 1);
+    }
+}
@@ -0,0 +1,81 @@
+// automated accounts, left out wherever metrics count people or pull requests
+use serde_json::Value;
+
+// GitHub account: apps have the "Bot" type and log in as "name[bot]",
+// others are listed by login in `accounts`
+pub fn is_bot_account(login: &str, account_type: Option<&str>, accounts: &[String]) -> bool {
+    account_type == Some("Bot")
+        || login.ends_with("[bot]")
+        || accounts.iter().any(|b| b.eq_ignore_ascii_case(login))
+}
+
+// `author` of a GraphQL node, with its `__typename`, or `user` of a REST response,
+// with its `type`; deleted accounts are null and not bots
+pub fn is_bot_user(user: &Value, accounts: &[String]) -> bool {
+    match user["login"].as_str() {
+        Some(login) => {
+            let account_type = user["__typename"].as_str().or(user["type"].as_str());
+            is_bot_account(login, account_type, accounts)
+        }
+        None => false,
+    }
+}
+
+// git identity: apps sign as "name[bot]" with a "[bot]@" noreply address,
+// others are listed by name or email in `accounts`
+pub fn is_bot_identity(name: &str, email: &str, accounts: &[String]) -> bool {
+    name.ends_with("[bot]")
+        || email.contains("[bot]@")
+        || accounts
+            .iter()
+            .any(|b| b.eq_ignore_ascii_case(name) || b.eq_ignore_ascii_case(email))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn accounts() {
+        let listed = ["renovate-runner".to_string()];
+        assert!(is_bot_account("dependabot", Some("Bot"), &[]));
+        assert!(is_bot_account("github-actions[bot]", None, &[]));
+        assert!(is_bot_account("Renovate-Runner", Some("User"), &listed));
+        assert!(!is_bot_account("alice", Some("User"), &listed));
+
+        assert!(is_bot_user(
+            &json!({ "login": "dependabot", "__typename": "Bot" }),
+            &[]
+        ));
+        assert!(is_bot_user(
+            &json!({ "login": "dependabot[bot]", "type": "Bot" }),
+            &[]
+        ));
+        assert!(!is_bot_user(
+            &json!({ "login": "alice", "__typename": "User" }),
+            &[]
+        ));
+        assert!(!is_bot_user(&Value::Null, &[]));
+    }
+
+    #[test]
+    fn identities() {
+        assert!(is_bot_identity(
+            "dependabot[bot]",
+            "x@users.noreply.github.com",
+            &[]
+        ));
+        assert!(is_bot_identity(
+            "Renovate",
+            "29139614+renovate[bot]@users.noreply.github.com",
+            &[]
+        ));
+        assert!(is_bot_identity(
+            "CI",
+            "ci@example.com",
+            &["ci@example.com".to_string()]
+        ));
+        assert!(!is_bot_identity("Alice", "alice@example.com", &[]));
+    }
+}
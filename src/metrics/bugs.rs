@@ -0,0 +1,211 @@
+// bug reports of a repository, from GitHub's issue search
+use serde::Serialize;
+use serde_json::Value;
+
+// search qualifier matching issues with any of the labels
+fn label_filter(labels: &[String]) -> String {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|l| {
+            if l.contains([' ', ',', ':']) {
+                format!("\"{}\"", l)
+            } else {
+                l.clone()
+            }
+        })
+        .collect();
+    format!("label:{}", labels.join(","))
+}
+
+// GraphQL string literal
+pub fn quoted(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// GitHub's search returns at most this many results
+pub const MAX_OPEN: usize = 1000;
+
+fn base(owner: &str, repo: &str, labels: &[String]) -> String {
+    format!("repo:{}/{} is:issue {}", owner, repo, label_filter(labels))
+}
+
+// search of the open bugs, oldest first so that long-waiting bugs are never left out
+fn open_search(base: &str, first: u32, after: Option<&str>) -> String {
+    let after = match after {
+        Some(a) => format!(", after: {}", quoted(a)),
+        None => String::new(),
+    };
+    format!(
+        "open: search(query: {}, type: ISSUE, first: {}{}) {{ issueCount \
+         pageInfo {{ hasNextPage endCursor }} nodes {{ ... on Issue {{ createdAt }} }} }}",
+        quoted(&format!("{} is:open sort:created-asc", base)),
+        first,
+        after
+    )
+}
+
+// one GraphQL request counting the bugs reported since `since` (YYYY-MM-DD)
+// by how they were closed, and fetching the creation dates of the
+// `first` oldest open bugs
+pub fn query(owner: &str, repo: &str, labels: &[String], since: &str, first: u32) -> String {
+    let base = base(owner, repo, labels);
+    let window = format!("{} created:>={}", base, since);
+    let q = format!(
+        "query {{ \
+         reported: search(query: {}, type: ISSUE) {{ issueCount }} \
+         completed: search(query: {}, type: ISSUE) {{ issueCount }} \
+         not_planned: search(query: {}, type: ISSUE) {{ issueCount }} \
+         {} }}",
+        quoted(&window),
+        quoted(&format!("{} is:closed reason:completed", window)),
+        quoted(&format!("{} is:closed reason:\"not planned\"", window)),
+        open_search(&base, first, None)
+    );
+    serde_json::json!({ "query": q }).to_string()
+}
+
+// next `first` open bugs after the cursor of the previous page
+pub fn open_query(owner: &str, repo: &str, labels: &[String], first: u32, after: &str) -> String {
+    let q = format!(
+        "query {{ {} }}",
+        open_search(&base(owner, repo, labels), first, Some(after))
+    );
+    serde_json::json!({ "query": q }).to_string()
+}
+
+// bug counts in the window and ages of open bugs
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Bugs {
+    pub reported: u64,
+    pub completed: u64,
+    pub not_planned: u64,
+    // open bugs of any age
+    pub open: u64,
+    // over the fetched open bugs, all of them up to MAX_OPEN
+    pub open_fetched: usize,
+    pub open_median_age_days: Option<f64>,
+    pub open_max_age_days: Option<f64>,
+}
+
+impl Bugs {
+    // ages of the open bugs created at `created` (seconds since the epoch)
+    pub fn with_open_ages(mut self, created: &[i64], now: i64) -> Bugs {
+        let ages: Vec<f64> = created
+            .iter()
+            .map(|t| (now - t).max(0) as f64 / 86400.0)
+            .collect();
+        self.open_fetched = ages.len();
+        self.open_median_age_days = crate::metrics::response::percentile(&ages, 50.0);
+        self.open_max_age_days = ages.iter().cloned().reduce(f64::max);
+        self
+    }
+}
+
+// counts of a response to `query`, None when the search failed
+pub fn parse(response: &Value) -> Option<Bugs> {
+    let data = &response["data"];
+    Some(Bugs {
+        reported: data["reported"]["issueCount"].as_u64()?,
+        completed: data["completed"]["issueCount"].as_u64()?,
+        not_planned: data["not_planned"]["issueCount"].as_u64()?,
+        open: data["open"]["issueCount"].as_u64()?,
+        ..Default::default()
+    })
+}
+
+// creation times of the open bugs of a response to `query` or `open_query`,
+// and the cursor of the next page if any
+pub fn open_page(response: &Value) -> (Vec<i64>, Option<String>) {
+    let open = &response["data"]["open"];
+    let created = open["nodes"]
+        .as_array()
+        .map(|nodes| {
+            nodes
+                .iter()
+                .filter_map(|n| chrono::DateTime::parse_from_rfc3339(n["createdAt"].as_str()?).ok())
+                .map(|t| t.timestamp())
+                .collect()
+        })
+        .unwrap_or_default();
+    let next = match open["pageInfo"]["hasNextPage"].as_bool() {
+        Some(true) => open["pageInfo"]["endCursor"]
+            .as_str()
+            .map(|c| c.to_string()),
+        _ => None,
+    };
+    (created, next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn labels() {
+        assert_eq!(label_filter(&["bug".to_string()]), "label:bug");
+        assert_eq!(
+            label_filter(&["bug".to_string(), "type: bug".to_string()]),
+            "label:bug,\"type: bug\""
+        );
+    }
+
+    #[test]
+    fn query_is_json() {
+        let q = query("o", "r", &["type: bug".to_string()], "2023-01-01", 100);
+        let v: Value = serde_json::from_str(&q).unwrap();
+        let q = v["query"].as_str().unwrap();
+        assert!(q.contains(r#"search(query: "repo:o/r is:issue label:\"type: bug\" created:>=2023-01-01 is:closed reason:\"not planned\"""#));
+        assert!(q.contains(r#"open: search(query: "repo:o/r is:issue label:\"type: bug\" is:open sort:created-asc", type: ISSUE, first: 100)"#));
+
+        let q = open_query("o", "r", &["bug".to_string()], 100, "Y3Vyc29yOjEwMA==");
+        let v: Value = serde_json::from_str(&q).unwrap();
+        let q = v["query"].as_str().unwrap();
+        assert!(q.contains(r#"first: 100, after: "Y3Vyc29yOjEwMA==")"#));
+        assert!(!q.contains("reported"));
+    }
+
+    #[test]
+    fn parse_counts() {
+        let now = chrono::DateTime::parse_from_rfc3339("2023-01-31T00:00:00Z")
+            .unwrap()
+            .timestamp();
+        let response = json!({ "data": {
+            "reported": { "issueCount": 10 },
+            "completed": { "issueCount": 6 },
+            "not_planned": { "issueCount": 2 },
+            "open": {
+                "issueCount": 4,
+                "pageInfo": { "hasNextPage": true, "endCursor": "c1" },
+                "nodes": [
+                    { "createdAt": "2022-01-31T00:00:00Z" },
+                    { "createdAt": "2023-01-21T00:00:00Z" },
+                    { "createdAt": "2023-01-30T00:00:00Z" }
+                ]
+            }
+        } });
+        let bugs = parse(&response).unwrap();
+        assert_eq!(bugs.reported, 10);
+        assert_eq!(bugs.completed, 6);
+        assert_eq!(bugs.not_planned, 2);
+        assert_eq!(bugs.open, 4);
+        assert!(parse(&json!({ "errors": [] })).is_none());
+
+        // the rest of the open bugs come with the next page
+        let (mut created, next) = open_page(&response);
+        assert_eq!(next.as_deref(), Some("c1"));
+        let page = json!({ "data": { "open": {
+            "issueCount": 4,
+            "pageInfo": { "hasNextPage": false, "endCursor": "c2" },
+            "nodes": [ { "createdAt": "2023-01-31T00:00:00Z" } ]
+        } } });
+        let (more, next) = open_page(&page);
+        assert_eq!(next, None);
+        created.extend(more);
+
+        let bugs = bugs.with_open_ages(&created, now);
+        assert_eq!(bugs.open_fetched, 4);
+        assert_eq!(bugs.open_median_age_days, Some(5.5));
+        assert_eq!(bugs.open_max_age_days, Some(365.0));
+    }
+}
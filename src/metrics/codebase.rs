@@ -0,0 +1,197 @@
+// static profile of a cloned code base: size, languages and complexity
+use crate::metrics::files::{self, File};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// largest source files reported
+const LARGEST: usize = 5;
+
+// directories holding copies of other projects' code
+const VENDOR_DIRS: [&str; 5] = [
+    "vendor",
+    "vendored",
+    "third_party",
+    "third-party",
+    "external",
+];
+
+// markers of generated code in the first lines of a file
+const GENERATED_MARKERS: [&str; 4] = [
+    "@generated",
+    "DO NOT EDIT",
+    "Code generated",
+    "auto-generated",
+];
+
+// source file, counted in the profile
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Source {
+    pub path: String,
+    pub lines: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Codebase {
+    // every file of the clone
+    pub files: usize,
+    // hand-written source files and their lines, by language
+    pub source_files: usize,
+    pub source_lines: usize,
+    pub lines_by_language: BTreeMap<&'static str, usize>,
+    pub largest_files: Vec<Source>,
+    // source files left out of the counts above
+    pub generated_files: usize,
+    pub vendored_files: usize,
+    // branches and loops per 100 source lines
+    pub decision_density: f64,
+}
+
+// whether a file holds another project's code
+pub fn is_vendored(path: &str) -> bool {
+    let mut parts: Vec<&str> = path.split('/').collect();
+    parts.pop();
+    parts
+        .iter()
+        .any(|p| VENDOR_DIRS.contains(&p.to_ascii_lowercase().as_str()))
+}
+
+// whether a file was generated, by its name or a marker in its first lines
+pub fn is_generated(path: &str, text: &str) -> bool {
+    let lower = path.to_ascii_lowercase();
+    if lower.ends_with(".min.js")
+        || lower.ends_with(".pb.go")
+        || lower.ends_with("_pb2.py")
+        || lower.contains(".generated.")
+        || lower.starts_with("dist/")
+    {
+        return true;
+    }
+    text.lines()
+        .take(5)
+        .any(|l| GENERATED_MARKERS.iter().any(|m| l.contains(m)))
+}
+
+// branches and loops of source code, counted by keyword and operator:
+// a rough cyclomatic complexity without parsing
+pub fn decision_points(text: &str) -> usize {
+    const KEYWORDS: [&str; 8] = [
+        "if", "for", "while", "case", "catch", "match", "elif", "except",
+    ];
+    let mut count = 0;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with("//") || line.starts_with('#') || line.starts_with('*') {
+            continue;
+        }
+        count += line
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|w| KEYWORDS.contains(w))
+            .count();
+        count += line.matches("&&").count() + line.matches("||").count();
+    }
+    count
+}
+
+pub fn profile(root: &Path) -> Codebase {
+    let all = files::list(root);
+    let mut c = Codebase {
+        files: all.len(),
+        ..Default::default()
+    };
+    let mut decisions = 0;
+    let mut sources = Vec::new();
+    for f in &all {
+        let language = match files::language(&f.path) {
+            Some(l) => l,
+            None => continue,
+        };
+        if is_vendored(&f.path) {
+            c.vendored_files += 1;
+            continue;
+        }
+        let text = read(root, f);
+        if is_generated(&f.path, &text) {
+            c.generated_files += 1;
+            continue;
+        }
+        let lines = text.lines().count();
+        c.source_files += 1;
+        c.source_lines += lines;
+        *c.lines_by_language.entry(language).or_default() += lines;
+        decisions += decision_points(&text);
+        sources.push(Source {
+            path: f.path.clone(),
+            lines,
+        });
+    }
+    sources.sort_by(|a, b| b.lines.cmp(&a.lines).then(a.path.cmp(&b.path)));
+    sources.truncate(LARGEST);
+    c.largest_files = sources;
+    if c.source_lines > 0 {
+        c.decision_density = decisions as f64 * 100.0 / c.source_lines as f64;
+    }
+    c
+}
+
+fn read(root: &Path, f: &File) -> String {
+    if f.size > files::MAX_SIZE {
+        return String::new();
+    }
+    std::fs::read_to_string(root.join(&f.path)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vendored_and_generated() {
+        assert!(is_vendored("vendor/github.com/x/y.go"));
+        assert!(is_vendored("src/third_party/zlib/inflate.c"));
+        assert!(!is_vendored("src/vendor.rs"));
+        assert!(is_generated("static/app.min.js", ""));
+        assert!(is_generated("api/api.pb.go", ""));
+        assert!(is_generated(
+            "src/parser.rs",
+            "// @generated by lalrpop\nfn x() {}"
+        ));
+        assert!(!is_generated("src/parser.rs", "fn x() {}\n"));
+    }
+
+    #[test]
+    fn decisions() {
+        let code = "// if this were a comment\n\
+                    fn f(x: u8) -> u8 {\n\
+                    if x > 1 && x < 5 { return 1; }\n\
+                    for i in 0..x { match i { _ => {} } }\n\
+                    let iffy = 0; x\n\
+                    }\n";
+        assert_eq!(decision_points(code), 4);
+    }
+
+    #[test]
+    fn profile_tree() {
+        let dir = std::env::temp_dir().join(format!("codebase_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.join("vendor/lib")).unwrap();
+        std::fs::write(dir.join("src/a.rs"), "fn a() {\nif true {}\n}\n").unwrap();
+        std::fs::write(dir.join("src/b.py"), "def b():\n    pass\n").unwrap();
+        std::fs::write(dir.join("src/gen.rs"), "// @generated\nfn g() {}\n").unwrap();
+        std::fs::write(dir.join("vendor/lib/c.c"), "int c;\n").unwrap();
+        std::fs::write(dir.join("README.md"), "# x\n").unwrap();
+
+        let c = profile(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(c.files, 5);
+        assert_eq!(c.source_files, 2);
+        assert_eq!(c.source_lines, 5);
+        assert_eq!(c.lines_by_language["Rust"], 3);
+        assert_eq!(c.lines_by_language["Python"], 2);
+        assert_eq!(c.largest_files[0].path, "src/a.rs");
+        assert_eq!(c.generated_files, 1);
+        assert_eq!(c.vendored_files, 1);
+        assert_eq!(c.decision_density, 20.0);
+    }
+}
@@ -0,0 +1,122 @@
+// community health files, as in GitHub's community profile
+// see https://docs.github.com/en/communities/setting-up-your-project-for-healthy-contributions
+use crate::metrics::files::File;
+use serde_json::Value;
+
+// community files looked for, by item
+pub const ITEMS: [&str; 6] = [
+    "contributing",
+    "code_of_conduct",
+    "issue_template",
+    "pull_request_template",
+    "governance",
+    "funding",
+];
+
+// directories GitHub looks in for community files
+const DIRS: [&str; 3] = ["", ".github/", "docs/"];
+
+// whether `path` is `name` with any extension in one of DIRS
+fn named(path: &str, name: &str) -> bool {
+    let lower = path.to_ascii_lowercase();
+    DIRS.iter().any(|d| {
+        lower
+            .strip_prefix(d)
+            .and_then(|rest| rest.strip_prefix(name))
+            .is_some_and(|ext| ext.is_empty() || (ext.starts_with('.') && !ext[1..].contains('/')))
+    })
+}
+
+// file of an item in the clone
+pub fn find<'a>(files: &'a [File], item: &str) -> Option<&'a str> {
+    let matches = |p: &str| match item {
+        "contributing" => named(p, "contributing"),
+        "code_of_conduct" => named(p, "code_of_conduct") || named(p, "code-of-conduct"),
+        "issue_template" => {
+            named(p, "issue_template")
+                || p.to_ascii_lowercase()
+                    .starts_with(".github/issue_template/")
+        }
+        "pull_request_template" => {
+            named(p, "pull_request_template")
+                || p.to_ascii_lowercase()
+                    .starts_with(".github/pull_request_template/")
+        }
+        "governance" => named(p, "governance"),
+        "funding" => p.eq_ignore_ascii_case(".github/funding.yml"),
+        _ => false,
+    };
+    files.iter().map(|f| f.path.as_str()).find(|p| matches(p))
+}
+
+// items present according to the community profile endpoint, which also
+// knows the defaults of the owner's .github repository
+pub fn profile_items(profile: &Value) -> Vec<&'static str> {
+    ITEMS
+        .iter()
+        .filter(|i| profile["files"][*i].is_object())
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn files(paths: &[&str]) -> Vec<File> {
+        paths
+            .iter()
+            .map(|p| File {
+                path: p.to_string(),
+                size: 1,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn find_items() {
+        let f = files(&[
+            "CONTRIBUTING.md",
+            ".github/CODE_OF_CONDUCT.md",
+            ".github/ISSUE_TEMPLATE/bug.yml",
+            "docs/pull_request_template.md",
+            ".github/FUNDING.yml",
+            "src/governance.rs",
+        ]);
+        assert_eq!(find(&f, "contributing"), Some("CONTRIBUTING.md"));
+        assert_eq!(
+            find(&f, "code_of_conduct"),
+            Some(".github/CODE_OF_CONDUCT.md")
+        );
+        assert_eq!(
+            find(&f, "issue_template"),
+            Some(".github/ISSUE_TEMPLATE/bug.yml")
+        );
+        assert_eq!(
+            find(&f, "pull_request_template"),
+            Some("docs/pull_request_template.md")
+        );
+        assert_eq!(find(&f, "funding"), Some(".github/FUNDING.yml"));
+        assert_eq!(find(&f, "governance"), None);
+        assert_eq!(
+            find(&files(&["CONTRIBUTING"]), "contributing"),
+            Some("CONTRIBUTING")
+        );
+        assert_eq!(find(&files(&["contributing.d/x.md"]), "contributing"), None);
+    }
+
+    #[test]
+    fn profile() {
+        let p = json!({
+            "health_percentage": 71,
+            "files": {
+                "code_of_conduct": { "key": "contributor_covenant" },
+                "contributing": null,
+                "issue_template": { "url": "x" },
+                "readme": { "url": "y" }
+            }
+        });
+        assert_eq!(profile_items(&p), ["code_of_conduct", "issue_template"]);
+    }
+}
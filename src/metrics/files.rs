@@ -0,0 +1,117 @@
+// files of a cloned repository
+use std::path::Path;
+
+// regular file, with its path relative to the repository root and '/'-separated
+#[derive(Debug, Clone, PartialEq)]
+pub struct File {
+    pub path: String,
+    pub size: u64,
+}
+
+// files larger than this are not read
+pub const MAX_SIZE: u64 = 1024 * 1024;
+
+// every regular file under `root`, sorted, without .git and node_modules
+// symbolic links are not followed
+pub fn list(root: &Path) -> Vec<File> {
+    let mut files = Vec::new();
+    walk(root, root, &mut files);
+    files
+}
+
+fn walk(root: &Path, dir: &Path, files: &mut Vec<File>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+    let mut entries: Vec<_> = entries.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let meta = match std::fs::symlink_metadata(&path) {
+            Ok(m) => m,
+            Err(_) => continue,
+        };
+        if meta.is_dir() {
+            let name = entry.file_name();
+            if name != ".git" && name != "node_modules" {
+                walk(root, &path, files);
+            }
+        } else if meta.is_file() {
+            let rel = path.strip_prefix(root).unwrap_or(&path);
+            let parts: Vec<_> = rel.iter().map(|p| p.to_string_lossy()).collect();
+            files.push(File {
+                path: parts.join("/"),
+                size: meta.len(),
+            });
+        }
+    }
+}
+
+// programming language of a source file, by extension
+pub fn language(path: &str) -> Option<&'static str> {
+    let ext = path.rsplit_once('.')?.1.to_ascii_lowercase();
+    let lang = match ext.as_str() {
+        "rs" => "Rust",
+        "js" | "mjs" | "cjs" | "jsx" => "JavaScript",
+        "ts" | "mts" | "cts" | "tsx" => "TypeScript",
+        "py" => "Python",
+        "go" => "Go",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "C++",
+        "cs" => "C#",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "swift" => "Swift",
+        "scala" => "Scala",
+        "sh" | "bash" => "Shell",
+        _ => return None,
+    };
+    Some(lang)
+}
+
+// number of lines of a file, 0 if it is too large or not text
+pub fn lines(root: &Path, file: &File) -> usize {
+    if file.size > MAX_SIZE {
+        return 0;
+    }
+    match std::fs::read_to_string(root.join(&file.path)) {
+        Ok(text) => text.lines().count(),
+        Err(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn languages() {
+        assert_eq!(language("src/main.rs"), Some("Rust"));
+        assert_eq!(language("lib/index.test.JS"), Some("JavaScript"));
+        assert_eq!(language("include/a.hpp"), Some("C++"));
+        assert_eq!(language("README.md"), None);
+        assert_eq!(language("Makefile"), None);
+    }
+
+    #[test]
+    fn list_tree() {
+        let dir = std::env::temp_dir().join(format!("files_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src/nested")).unwrap();
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        std::fs::create_dir_all(dir.join("node_modules/x")).unwrap();
+        std::fs::write(dir.join("src/nested/b.rs"), "fn b() {}\n").unwrap();
+        std::fs::write(dir.join("src/a.rs"), "fn a() {}\nfn c() {}\n").unwrap();
+        std::fs::write(dir.join(".git/HEAD"), "ref").unwrap();
+        std::fs::write(dir.join("node_modules/x/index.js"), "").unwrap();
+
+        let files = list(&dir);
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["src/a.rs", "src/nested/b.rs"]);
+        assert_eq!(lines(&dir, &files[0]), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
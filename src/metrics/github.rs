@@ -1,9 +1,22 @@
-use crate::metrics::Metrics;
+use crate::config;
+use crate::license::classify;
+use crate::license::deps::{self, Conflict, Dependency, Ecosystem};
+use crate::metrics::codebase::{self, Codebase};
+use crate::metrics::plugins::Target;
+use crate::metrics::security::{self, Check};
+use crate::metrics::{
+    advisories, bugs, community, files, history, pinning, popularity, releases, response, review,
+    testing, Explain, Explanation, Metrics,
+};
 use chrono::offset::Utc;
 use log::{debug, info};
 use reqwest::header;
-use statrs::distribution::{ContinuousCDF, Normal};
+use serde_json::json;
+use std::cell::{OnceCell, RefCell};
+use std::collections::BTreeMap;
 use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[derive(Debug)]
 pub struct Github {
@@ -11,40 +24,116 @@ pub struct Github {
     owner: String,
     repo: String,
     link: String,
+    // tag, branch or commit to evaluate instead of the default branch
+    git_ref: Option<String>,
+    // name of the package published from the repository, if any
+    package: Option<String>,
+    // version of the package evaluated
+    version: Option<String>,
+    // SPDX expression declared by the package, preferred over github's detection
+    declared_license: Option<String>,
+    // dependencies declared by the package, read from the clone otherwise
+    dependencies: Option<(Ecosystem, Vec<Dependency>)>,
+    // publish time of each version of the package, in seconds since the epoch
+    version_times: BTreeMap<String, i64>,
 
     // API-related
     client: reqwest::blocking::Client,
+
+    // local clone shared by file-based metrics
+    checkout: OnceCell<Option<Checkout>>,
+    // profile of the clone, computed on first use
+    codebase: OnceCell<Option<Codebase>>,
+
+    // how each metric calculated so far arrived at its value
+    explain: RefCell<Explain>,
+    // dependencies whose licenses the policy doesn't allow
+    conflicts: RefCell<Vec<Conflict>>,
+    // outcome of each security check
+    checks: RefCell<Vec<Check>>,
+    // whether each community item was found
+    community_items: RefCell<BTreeMap<String, bool>>,
 }
 
-impl Github {
-    // create new instance with url
-    pub fn with_url(url: &str) -> Option<Github> {
-        let u = reqwest::Url::parse(url).ok()?;
+// cloned repository, deleted when dropped
+#[derive(Debug)]
+pub struct Checkout {
+    path: PathBuf,
+}
 
-        // check if domain is "github.com"
-        if let Some(domain) = u.domain() {
-            if domain != "github.com" {
-                return None;
-            }
-        } else {
-            return None;
-        }
+impl Checkout {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
 
-        // check if scheme is https or http
-        let sch = u.scheme();
-        if sch != "https" && sch != "http" {
-            return None;
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        if std::fs::remove_dir_all(&self.path).is_ok() {
+            info!("repository deleted");
         }
+    }
+}
+
+// extract (owner, repo, ref) from a github url
+// the ref is taken from paths like /tree/<ref>, /commit/<sha> or /releases/tag/<tag>
+pub fn parse_url(url: &str) -> Option<(String, String, Option<String>)> {
+    let u = reqwest::Url::parse(url).ok()?;
 
-        // extract repo info from url
-        let mut path = u.path().split('/').skip(1);
+    // check if domain is "github.com"
+    if u.domain()? != "github.com" {
+        return None;
+    }
+
+    // check if scheme is https or http
+    let sch = u.scheme();
+    if sch != "https" && sch != "http" {
+        return None;
+    }
+
+    // extract repo info from url
+    let mut path = u.path().split('/').skip(1).filter(|s| !s.is_empty());
+    let owner = path.next()?.to_string();
+    let repo = path.next()?.to_string();
+
+    // the rest of the path may name a ref
+    // /tree/<ref>/<path> points into a directory, so only the first segment is
+    // the ref; branches with a slash in their name can't be told apart from it
+    let rest: Vec<&str> = path.collect();
+    let git_ref = match rest.as_slice() {
+        ["tree", r, ..] | ["commit", r, ..] => Some(r.to_string()),
+        ["releases", "tag", r @ ..] if !r.is_empty() => Some(r.join("/")),
+        _ => None,
+    };
+
+    Some((owner, repo, git_ref))
+}
+
+// `key=value` with the value percent-encoded, for refs like "v1.0+build" or "a#b"
+fn query_param(key: &str, value: &str) -> String {
+    let mut u = reqwest::Url::parse("http://localhost/").unwrap();
+    u.query_pairs_mut().append_pair(key, value);
+    u.query().unwrap_or_default().to_string()
+}
+
+// value percent-encoded as one path segment, for branches like "a#b" or "100%"
+fn path_segment(value: &str) -> String {
+    let mut u = reqwest::Url::parse("http://localhost/").unwrap();
+    if let Ok(mut segments) = u.path_segments_mut() {
+        segments.clear().push(value);
+    }
+    u.path().trim_start_matches('/').to_string()
+}
+
+impl Github {
+    // create new instance with url
+    pub fn with_url(url: &str) -> Option<Github> {
+        let (owner, repo, git_ref) = parse_url(url)?;
         let link = url.to_string();
-        let owner = path.next()?.to_string();
-        let repo = path.next()?.to_string();
 
         // http client
         let mut headers = header::HeaderMap::new();
-        let t = format!("Bearer {}", std::env::var("GITHUB_TOKEN").ok()?);
+        let t = format!("Bearer {}", config::get().github.token.as_ref()?);
         headers.insert(
             header::AUTHORIZATION,
             header::HeaderValue::from_str(&t).ok()?,
@@ -67,17 +156,119 @@ impl Github {
             owner,
             repo,
             link,
+            git_ref,
+            package: None,
+            version: None,
+            declared_license: None,
+            dependencies: None,
+            version_times: BTreeMap::new(),
             client,
+            checkout: OnceCell::new(),
+            codebase: OnceCell::new(),
+            explain: RefCell::new(Explain::new()),
+            conflicts: RefCell::new(Vec::new()),
+            checks: RefCell::new(Vec::new()),
+            community_items: RefCell::new(BTreeMap::new()),
         })
     }
 
+    // evaluate the repository at a tag, branch or commit
+    pub fn at_ref(mut self, git_ref: &str) -> Github {
+        self.git_ref = Some(git_ref.to_string());
+        self
+    }
+
+    // name of the package published from the repository
+    pub fn for_package(mut self, package: &str) -> Github {
+        self.package = Some(package.to_string());
+        self
+    }
+
+    // version of the package the repository is evaluated for
+    pub fn with_version(mut self, version: &str) -> Github {
+        self.version = Some(version.to_string());
+        self
+    }
+
+    // license expression declared in package metadata
+    pub fn with_declared_license(mut self, license: &str) -> Github {
+        self.declared_license = Some(license.to_string());
+        self
+    }
+
+    // runtime dependencies declared in package metadata
+    pub fn with_dependencies(
+        mut self,
+        ecosystem: Ecosystem,
+        dependencies: Vec<Dependency>,
+    ) -> Github {
+        self.dependencies = Some((ecosystem, dependencies));
+        self
+    }
+
+    // times the package's versions were published
+    pub fn with_version_times(mut self, times: BTreeMap<String, i64>) -> Github {
+        self.version_times = times;
+        self
+    }
+
+    // declared dependencies, from package.json or Cargo.toml in the clone
+    // unless given by package metadata
+    fn dependencies(&self) -> Option<(Ecosystem, Vec<Dependency>)> {
+        if let Some(d) = &self.dependencies {
+            return Some(d.clone());
+        }
+        let path = self.checkout()?.path();
+        if let Ok(m) = std::fs::read_to_string(path.join("package.json")) {
+            let m: serde_json::Value = serde_json::from_str(&m).ok()?;
+            return Some((Ecosystem::Npm, deps::npm_dependencies(&m)));
+        }
+        let m = std::fs::read_to_string(path.join("Cargo.toml")).ok()?;
+        // an unparsable manifest is no manifest, not one without dependencies
+        m.parse::<toml::Table>().ok()?;
+        Some((Ecosystem::Crates, deps::cargo_dependencies(&m)))
+    }
+
+    // OSV ecosystem, name and version of the package, from package metadata
+    // or the clone's package.json or Cargo.toml
+    fn package_identity(&self) -> Option<(&'static str, String, Option<String>)> {
+        if let Some(p) = &self.package {
+            return Some(("npm", p.clone(), self.version.clone()));
+        }
+        let path = self.checkout()?.path();
+        let field = |v: &serde_json::Value| v.as_str().map(|s| s.to_string());
+        if let Ok(m) = std::fs::read_to_string(path.join("package.json")) {
+            let m: serde_json::Value = serde_json::from_str(&m).ok()?;
+            return Some(("npm", field(&m["name"])?, field(&m["version"])));
+        }
+        let m = std::fs::read_to_string(path.join("Cargo.toml")).ok()?;
+        let m: toml::Table = m.parse().ok()?;
+        let package = m.get("package")?;
+        let name = package.get("name")?.as_str()?.to_string();
+        let version = package
+            .get("version")
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string());
+        Some(("crates.io", name, version))
+    }
+
+    // names the module is known by in policies: the package name and "owner/repo"
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.package.iter().cloned().collect();
+        names.push(format!("{}/{}", self.owner, self.repo));
+        names
+    }
+
     // GitHub REST API
     // https://docs.github.com/en/rest?apiVersion=2022-11-28
     pub fn rest_api(&self, path: &str) -> reqwest::Result<reqwest::blocking::Response> {
         self.client
             .get(format!(
-                "https://api.github.com/repos/{}/{}/{}",
-                self.owner, self.repo, path
+                "{}/repos/{}/{}/{}",
+                config::get().github.api_url.trim_end_matches('/'),
+                self.owner,
+                self.repo,
+                path
             ))
             .send()
     }
@@ -90,8 +281,7 @@ impl Github {
     // GitHub GraphQL API
     pub fn graphql(&self, query: String) -> reqwest::Result<reqwest::blocking::Response> {
         self.client
-            .post("https://api.github.com/graphql")
-            .bearer_auth(format!("{}", std::env::var("GITHUB_TOKEN").unwrap()))
+            .post(&config::get().github.graphql_url)
             .body(query)
             .send()
     }
@@ -111,12 +301,11 @@ impl Github {
                 .json::<serde_json::Value>()?
                 .as_array()
                 .unwrap()
-                .len()
-                != 0
+                .is_empty()
             {
-                return Ok(1);
-            } else {
                 return Ok(0);
+            } else {
+                return Ok(1);
             }
         }
 
@@ -127,41 +316,200 @@ impl Github {
 
         Ok(page.unwrap().parse::<u32>().unwrap())
     }
-}
-impl Metrics for Github {
-    fn ramp_up_time(&self) -> f64 {
-        // Specify the path of repo to clone into
-        let path_name = format!("cloned_{}_{}", self.owner, self.repo);
-        let repo_path = std::path::Path::new(&path_name);
+
+    // remember how a metric was calculated, returning its value
+    fn explained(&self, metric: &str, inputs: serde_json::Value, formula: &str, value: f64) -> f64 {
+        self.explain
+            .borrow_mut()
+            .insert(metric.to_string(), Explanation::new(inputs, formula, value));
+        value
+    }
+
+    // local clone of the repository at the requested ref
+    // cloned on first use and shared between metrics
+    pub fn checkout(&self) -> Option<&Checkout> {
+        self.checkout.get_or_init(|| self.clone_repo()).as_ref()
+    }
+
+    // size, languages and complexity of the clone
+    fn profile(&self) -> Option<&Codebase> {
+        self.codebase
+            .get_or_init(|| {
+                let checkout = self.checkout()?;
+                info!("profiling the code base");
+                Some(codebase::profile(checkout.path()))
+            })
+            .as_ref()
+    }
+
+    fn clone_repo(&self) -> Option<Checkout> {
+        // Specify the path of repo to clone into, unique within this process
+        // since modules may be scored concurrently
+        static CLONES: AtomicUsize = AtomicUsize::new(0);
+        let dir = format!(
+            "cloned_{}_{}_{}_{}",
+            self.owner,
+            self.repo,
+            std::process::id(),
+            CLONES.fetch_add(1, Ordering::SeqCst)
+        );
+        let cache_dir = &config::get().cache_dir;
+        if let Err(e) = std::fs::create_dir_all(cache_dir) {
+            info!("failed to create {}: {}", cache_dir.display(), e);
+            return None;
+        }
+        let checkout = Checkout {
+            path: cache_dir.join(dir),
+        };
 
         // Clone the repo
-        info!("cloning repository from {}", &self.link);
-        git2::Repository::clone(&self.link, repo_path).unwrap();
+        let url = format!("https://github.com/{}/{}", self.owner, self.repo);
+        info!("cloning repository for {} from {}", self.link, url);
+        let repo = match git2::Repository::clone(&url, checkout.path()) {
+            Ok(repo) => repo,
+            Err(e) => {
+                info!("failed to clone {}: {}", url, e);
+                return None;
+            }
+        };
         info!("repository cloned");
 
-        // Check if there is readme
-        let file = match std::fs::File::open(&format!("{}/README.md", path_name)) {
-            Ok(file) => file,
-            Err(_) => {
-                std::fs::remove_dir_all(repo_path).unwrap();
-                return 0.0;
+        // switch to the requested ref, trying remote branches after tags and commits
+        if let Some(r) = &self.git_ref {
+            let candidates = [r.clone(), format!("origin/{}", r)];
+            let object = candidates
+                .iter()
+                .find_map(|c| repo.revparse_single(c).ok())
+                .and_then(|o| o.peel_to_commit().ok());
+            let commit = match object {
+                Some(c) => c,
+                None => {
+                    info!("ref {} not found in {}", r, url);
+                    return None;
+                }
+            };
+            let mut opts = git2::build::CheckoutBuilder::new();
+            repo.checkout_tree(commit.as_object(), Some(opts.force()))
+                .ok()?;
+            repo.set_head_detached(commit.id()).ok()?;
+            info!("checked out {} at {}", r, commit.id());
+        }
+
+        Some(checkout)
+    }
+}
+impl Metrics for Github {
+    fn ramp_up_time(&self) -> f64 {
+        let checkout = match self.checkout() {
+            Some(c) => c,
+            None => {
+                return self.explained(
+                    "RAMP_UP_SCORE",
+                    json!({}),
+                    "0 if the repository can't be cloned",
+                    0.0,
+                )
             }
         };
-        let reader = std::io::BufReader::new(file);
+
+        // Check if there is readme
+        let readme_lines = std::fs::File::open(checkout.path().join("README.md"))
+            .ok()
+            .map(|file| std::io::BufReader::new(file).lines().count());
 
         // Get the # of lines and calculate the score
         info!("calculating ramp_up_score");
-        let lines = reader.lines().count();
-        let result = Self::calc_ramp_up_time(lines.try_into().unwrap_or(u32::MAX));
-        std::fs::remove_dir_all(repo_path).unwrap();
+        let readme = match readme_lines {
+            Some(lines) => Self::calc_ramp_up_time(lines.try_into().unwrap_or(u32::MAX)),
+            None => 0.0,
+        };
+        let weight = config::get().ramp_up.codebase_weight;
+        let profile = match self.profile() {
+            Some(p) if weight > 0.0 => p,
+            _ => {
+                debug!("ramp_up_score: {:.2}", readme);
+                return self.explained(
+                    "RAMP_UP_SCORE",
+                    json!({ "readme_lines": readme_lines }),
+                    "pdf(x) * sqrt(x) / 0.2613 with x = readme_lines / 150 * 0.7, pdf = standard normal density, 0 without README.md",
+                    readme,
+                );
+            }
+        };
+        let code = Self::calc_codebase_ramp_up(profile.source_lines, profile.decision_density);
+        let result = (1.0 - weight) * readme + weight * code;
         debug!("ramp_up_score: {:.2}", result);
-        info!("repository deleted");
-        result
+        self.explained(
+            "RAMP_UP_SCORE",
+            json!({
+                "readme_lines": readme_lines,
+                "source_lines": profile.source_lines,
+                "decision_density": profile.decision_density,
+                "codebase_weight": weight,
+            }),
+            "(1 - codebase_weight) * readme + codebase_weight * (0.5 * 0.5^(source_lines / 50000) + 0.5 * 0.5^(decision_density / 20)), readme = pdf(x) * sqrt(x) / 0.2613 with x = readme_lines / 150 * 0.7, 0 without README.md",
+            result,
+        )
     }
 
     fn correctness(&self) -> f64 {
-        // issues returns pull requests as well, so subtract pulls from issues
+        // bug reports in the window and open bugs with GraphQL API
         info!("calculating correctness_score");
+        let c = &config::get().correctness;
+        let since =
+            (Utc::now() - chrono::naive::Days::new(c.window_days.into())).format("%Y-%m-%d");
+        let q = bugs::query(
+            &self.owner,
+            &self.repo,
+            &c.bug_labels,
+            &since.to_string(),
+            100,
+        );
+        let response = self.graph_json(q).ok();
+        let found = response
+            .as_ref()
+            .and_then(bugs::parse)
+            .filter(|b| b.reported > 0 || b.open > 0);
+        if let (Some(b), Some(response)) = (found, response) {
+            // every open bug, oldest first, as far as the search goes
+            let (mut created, mut after) = bugs::open_page(&response);
+            while let Some(a) = after.take() {
+                if created.len() >= bugs::MAX_OPEN {
+                    break;
+                }
+                let q = bugs::open_query(&self.owner, &self.repo, &c.bug_labels, 100, &a);
+                let page = match self.graph_json(q) {
+                    Ok(r) => bugs::open_page(&r),
+                    Err(_) => break,
+                };
+                created.extend(page.0);
+                after = page.1;
+            }
+            created.truncate(bugs::MAX_OPEN);
+            let b = b.with_open_ages(&created, Utc::now().timestamp());
+            let result = Self::calc_bug_correctness(
+                b.reported,
+                b.completed,
+                b.not_planned,
+                b.open_median_age_days,
+            );
+            debug!("correctness_score: {:.2}", result);
+            let mut inputs = json!(b);
+            inputs["window_days"] = json!(c.window_days);
+            inputs["bug_labels"] = json!(c.bug_labels);
+            return self.explained(
+                "CORRECTNESS_SCORE",
+                inputs,
+                "0.7 * fixed + 0.3 * 0.5^(open_median_age_days / 180), \
+                 fixed = completed / (reported - not_planned) over bugs reported in the window \
+                 (1 if none), open_median_age_days over all open bugs, oldest first up to \
+                 GitHub's search limit of 1000 (0 if none)",
+                result,
+            );
+        }
+
+        // without labelled bugs, all issues ever opened
+        // issues returns pull requests as well, so subtract pulls from issues
         let all = self.rest_page_count("issues?state=all&per_page=1").unwrap()
             - self.rest_page_count("pulls?state=all&per_page=1").unwrap();
         let closed = self
@@ -172,56 +520,592 @@ impl Metrics for Github {
                 .unwrap();
         let result = Self::calc_correctness(all, closed);
         debug!("correctness_score: {:.2}", result);
-        result
+        self.explained(
+            "CORRECTNESS_SCORE",
+            json!({ "issues_all": all, "issues_closed": closed }),
+            "issues_closed / issues_all, 0 without issues; used when no issue has a bug label",
+            result,
+        )
     }
 
     fn bus_factor(&self) -> f64 {
-        // call graphql api to get the data specified in the query
         info!("calculating bus_factor_score");
+        let formula = "2 * truck_factor / (truck_factor + 1) - 1, truck_factor = fewest authors \
+                       covering share of the non-bot commits in the window_days before HEAD";
+
+        // authorship of recent commits, after .mailmap aliases and without bots
+        let c = &config::get().bus_factor;
+        let mut accounts = config::get().bots.accounts.clone();
+        accounts.extend(c.bots.iter().cloned());
+        let history = self
+            .checkout()
+            .and_then(|checkout| history::authors(checkout.path(), c.window_days, &accounts).ok());
+        if let Some(history) = history {
+            let truck_factor = history::truck_factor(&history.authors, c.share);
+            let score = Self::calc_bus_factor(truck_factor as u32);
+            debug!("bus_factor_score: {:.2}", score);
+            let top: Vec<_> = history
+                .authors
+                .iter()
+                .take(truck_factor.max(1))
+                .map(|a| json!({ "name": a.name, "commits": a.commits }))
+                .collect();
+            return self.explained(
+                "BUS_FACTOR_SCORE",
+                json!({
+                    "truck_factor": truck_factor,
+                    "authors": history.authors.len(),
+                    "commits": history.commits(),
+                    "bot_commits": history.bot_commits,
+                    "window_days": c.window_days,
+                    "share": c.share,
+                    "top_authors": top,
+                }),
+                formula,
+                score,
+            );
+        }
+
+        // without a clone, count everyone who interacted with the repository
         let bus = self.graph_json(
             format!("{{\"query\" : \"query {{ repository(owner:\\\"{}\\\", name:\\\"{}\\\") {{ mentionableUsers {{ totalCount }} }} }}\" }}", self.owner, self.repo)
             ).unwrap();
         let collaborators = bus["data"]["repository"]["mentionableUsers"]["totalCount"]
             .as_i64()
             .unwrap();
-        // calculate the score for bus factor
-        let score: f64 = ((2.0 * collaborators as f64) / (collaborators as f64 + 1.0)) - 1.0;
+        let score = Self::calc_bus_factor(collaborators.try_into().unwrap_or(0));
         debug!("bus_factor_score: {:.2}", score);
-        return score;
+        self.explained(
+            "BUS_FACTOR_SCORE",
+            json!({ "mentionable_users": collaborators }),
+            "2 * mentionable_users / (mentionable_users + 1) - 1, used when the repository can't be cloned",
+            score,
+        )
     }
 
     fn responsiveness(&self) -> f64 {
-        // get pull requests last year with GraphQL API
-        // source of query:
-        // https://stackoverflow.com/questions/61477294/how-to-filter-github-pull-request-by-updated-date-using-graphql
+        // issues and pull requests created in the window with GraphQL API
         info!("calculating responsive_maintainer_score");
-        let a_year_ago = (Utc::now() - chrono::naive::Days::new(365)).format("%Y-%m-%d");
-        let json = self.graph_json(
-            format!("{{\"query\" : \"query {{ search(query: \\\"repo:{}/{} is:pr updated:>={}\\\" type:ISSUE) {{ issueCount }} }}\" }}", self.owner, self.repo, a_year_ago)
-            ).unwrap();
-        let pulls = json["data"]["search"]["issueCount"].as_f64().unwrap();
-
-        let normal = Normal::new(0.0, 1.0).unwrap();
+        let c = &config::get().responsiveness;
+        let bots = &config::get().bots.accounts;
+        let since =
+            (Utc::now() - chrono::naive::Days::new(c.window_days.into())).format("%Y-%m-%d");
+        let mut inputs = json!({ "window_days": c.window_days });
+        let mut all = Vec::new();
+        for kind in ["issue", "pr"] {
+            let q = response::query(&self.owner, &self.repo, kind, &since.to_string(), c.sample);
+            let items = response::items(&self.graph_json(q).unwrap(), bots);
+            inputs[kind] = json!(response::summarize(&items, Utc::now().timestamp()));
+            all.extend(items);
+        }
+        let summary = response::summarize(&all, Utc::now().timestamp());
 
-        let result = normal.cdf(pulls / 13.0 - 2.0);
+        let result =
+            Self::calc_responsiveness(summary.response_median_days, summary.close_median_days);
         debug!("responsive_maintainer_score: {:.2}", result);
-        result
+        inputs["all"] = json!(summary);
+        self.explained(
+            "RESPONSIVE_MAINTAINER_SCORE",
+            inputs,
+            "0.5 * 0.5^(response_median_days / 7) + 0.5 * 0.5^(close_median_days / 30) \
+             over issues and pull requests created in the window and not opened by bots; \
+             a response is the first comment or review of a maintainer other than the author \
+             or a bot, or closing; \
+             unanswered and open items count with the time waited so far",
+            result,
+        )
     }
 
     fn compatibility(&self) -> f64 {
-        // get license with github api
+        // get license with github api, at the requested ref if any
         info!("calculating license_score");
-        let l = self.rest_json("license").unwrap();
-        let license = l["license"]["spdx_id"].as_str();
+        let path = match &self.git_ref {
+            Some(r) => format!("license?{}", query_param("ref", r)),
+            None => "license".to_string(),
+        };
+        // a failed request leaves the license to the package and the license files
+        let l = self.rest_json(&path).unwrap_or_else(|e| {
+            info!("failed to get the license: {}", e);
+            serde_json::Value::Null
+        });
+        let spdx_id = l["license"]["spdx_id"].as_str();
+        let formula = "policy decision on the declared license expression, or spdx_id, \
+                       or the license detected in the repository's files: \
+                       allow = 1, review = review_score, deny = 0, \
+                       OR picks the best term, AND the worst";
+
+        // the package's own declaration is more precise than github's detection
+        let mut license = self
+            .declared_license
+            .clone()
+            .or(spdx_id.map(|l| l.to_string()));
+
+        // match license files against known texts when github can't tell
+        let mut detected = None;
+        if license
+            .as_deref()
+            .is_none_or(|l| l.eq_ignore_ascii_case("NOASSERTION"))
+        {
+            info!("classifying license files");
+            detected = self.checkout().and_then(|c| classify::detect(c.path()));
+            if let Some(m) = &detected {
+                debug!("detected {} in {} ({:.2})", m.license, m.file, m.confidence);
+                license = Some(m.license.to_string());
+            }
+        }
+        let detected = detected
+            .map(|m| json!({ "license": m.license, "file": m.file, "confidence": m.confidence }));
+
+        let names = self.names();
+        let names: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+        let (mut result, mut inputs) = match &license {
+            Some(license) => {
+                let (result, verdict) = Self::calc_compatibility(&names, license);
+                debug!("license_score: {:.2} ({})", result, verdict.rule);
+                let inputs = json!({
+                    "spdx_id": spdx_id,
+                    "declared_license": self.declared_license,
+                    "detected": detected,
+                    "decision": verdict.decision,
+                    "rule": verdict.rule,
+                });
+                (result, inputs)
+            }
+            // no license found, the dependencies are still reported
+            None => (0.0, json!({ "spdx_id": null, "detected": detected })),
+        };
+
+        // a dependency the policy doesn't allow lowers the score to its own
+        let depth = config::get().license_depth;
+        if depth > 0 {
+            info!("checking licenses of dependencies");
+            let root = names[0];
+            let report = match (self.dependencies(), deps::HttpRegistry::new()) {
+                (Some((ecosystem, d)), Some(registry)) => deps::check(
+                    &registry,
+                    &config::get().license,
+                    ecosystem,
+                    root,
+                    &d,
+                    depth,
+                ),
+                _ => deps::Report {
+                    score: 1.0,
+                    ..Default::default()
+                },
+            };
+            debug!(
+                "{} dependencies checked, {} conflicts, {} unresolved",
+                report.checked,
+                report.conflicts.len(),
+                report.unresolved.len()
+            );
+            result = result.min(report.score);
+            inputs["dependencies"] = json!({
+                "depth": depth,
+                "checked": report.checked,
+                "score": report.score,
+                "conflicts": report.conflicts,
+                "unresolved": report.unresolved,
+            });
+            *self.conflicts.borrow_mut() = report.conflicts;
+        }
+
+        self.explained(
+            "LICENSE_SCORE",
+            inputs,
+            &format!(
+                "{}; with --license-depth, the lowest of that and the scores of the \
+                 dependencies the registry could resolve",
+                formula
+            ),
+            result,
+        )
+    }
 
-        // no license found
-        if license.is_none() {
-            return 0.0;
+    fn testing(&self) -> f64 {
+        info!("calculating testing_score");
+        let checkout = match self.checkout() {
+            Some(c) => c,
+            None => {
+                return self.explained(
+                    "TESTING_SCORE",
+                    json!({}),
+                    "0 if the repository can't be cloned",
+                    0.0,
+                )
+            }
+        };
+        let t = testing::scan(checkout.path());
+
+        // conclusion of the last completed GitHub Actions run of the branch,
+        // or of the commit when evaluating a ref
+        let mut ci_status = None;
+        if t.ci.contains(&"github_actions") {
+            let path = match history::head(checkout.path()) {
+                Ok((Some(branch), _)) => Some(format!(
+                    "actions/runs?{}&status=completed&per_page=1",
+                    query_param("branch", &branch)
+                )),
+                Ok((None, id)) => Some(format!(
+                    "actions/runs?head_sha={}&status=completed&per_page=1",
+                    id
+                )),
+                Err(_) => None,
+            };
+            ci_status = path.and_then(|p| self.rest_json(&p).ok()).and_then(|r| {
+                r["workflow_runs"][0]["conclusion"]
+                    .as_str()
+                    .map(|c| c.to_string())
+            });
         }
+        let ci_passed = ci_status.as_deref().map(|c| c == "success");
 
-        let result = Self::calc_compatibility(&license.unwrap());
-        debug!("license_score: {:.2}", result);
-        result
+        let result = Self::calc_testing(t.test_files, t.ratio(), !t.ci.is_empty(), ci_passed);
+        debug!("testing_score: {:.2}", result);
+        let mut inputs = json!(t);
+        inputs["test_ratio"] = json!(t.ratio());
+        inputs["ci_status"] = json!(ci_status);
+        self.explained(
+            "TESTING_SCORE",
+            inputs,
+            "0.5 * min(1, test_ratio / 0.3) with test files, test_ratio = test_lines / source_lines; \
+             plus with CI configured 0.5 if its last run passed, 0.3 if it failed, 0.4 if unknown",
+            result,
+        )
+    }
+
+    fn pinning(&self) -> f64 {
+        // requirements of the published version or of the clone's manifest
+        info!("calculating pinning_score");
+        let (ecosystem, deps) = match self.dependencies() {
+            Some((e, d)) => (Some(e), d),
+            None => (None, Vec::new()),
+        };
+        let unpinned: Vec<_> = match ecosystem {
+            Some(e) => pinning::unpinned(&deps, e)
+                .iter()
+                .map(|d| json!({ "name": d.name, "requirement": d.requirement }))
+                .collect(),
+            None => Vec::new(),
+        };
+        let total = ecosystem.map(|_| deps.len());
+        let result = Self::calc_pinning(total, deps.len() - unpinned.len());
+        debug!("pinning_score: {:.2}", result);
+        self.explained(
+            "PINNING_SCORE",
+            json!({
+                "ecosystem": ecosystem.map(|e| format!("{:?}", e).to_lowercase()),
+                "dependencies": deps.len(),
+                "pinned": deps.len() - unpinned.len(),
+                "unpinned": unpinned,
+            }),
+            "pinned / dependencies, 1 without dependencies, 0 without a readable manifest; \
+             a requirement is pinned when it only allows one major.minor version, \
+             e.g. ~1.2.3, 1.2.x or ^0.2.3",
+            result,
+        )
+    }
+
+    fn review(&self) -> f64 {
+        // commits to the default branch in the window with GraphQL API
+        info!("calculating review_score");
+        let c = &config::get().review;
+        let since = (Utc::now() - chrono::naive::Days::new(c.window_days.into()))
+            .format("%Y-%m-%dT%H:%M:%SZ")
+            .to_string();
+        let mut commits = Vec::new();
+        let mut after = None;
+        while commits.len() < c.max_commits as usize {
+            let q = review::query(&self.owner, &self.repo, &since, after.as_deref());
+            let page = self
+                .graph_json(q)
+                .ok()
+                .and_then(|r| review::parse(&r, &config::get().bots.accounts));
+            let (page, next) = match page {
+                Some(p) => p,
+                None => break,
+            };
+            commits.extend(page);
+            after = match next {
+                Some(n) => Some(n),
+                None => break,
+            };
+        }
+        commits.truncate(c.max_commits as usize);
+        let r = review::summarize(&commits);
+
+        let result = Self::calc_review(r.additions, r.reviewed_additions);
+        debug!("review_score: {:.2}", result);
+        let mut inputs = json!(r);
+        inputs["window_days"] = json!(c.window_days);
+        self.explained(
+            "REVIEW_SCORE",
+            inputs,
+            "reviewed_additions / additions over the non-merge commits of people to the default \
+             branch in the window, reviewed when they came with a merged pull request that has \
+             an approving review by someone other than a bot; 0 without additions",
+            result,
+        )
+    }
+
+    fn maintenance(&self) -> f64 {
+        // archive state, last commit, releases and tags with GraphQL API
+        info!("calculating maintenance_score");
+        let q = releases::query(&self.owner, &self.repo);
+        let repo = match self.graph_json(q) {
+            Ok(r) => releases::parse(&r).ok_or_else(|| {
+                let message = r["errors"][0]["message"].as_str().or(r["message"].as_str());
+                message.unwrap_or("unexpected response").to_string()
+            }),
+            Err(e) => Err(e.to_string()),
+        };
+        // a failed request says nothing about the project, so it isn't scored as unmaintained
+        let repo = match repo {
+            Ok(r) => r,
+            Err(e) => {
+                info!("failed to fetch the activity of {}: {}", self.link, e);
+                return self.explained(
+                    "MAINTENANCE_SCORE",
+                    json!({ "error": e }),
+                    "unknown when the repository's activity can't be fetched",
+                    f64::NAN,
+                );
+            }
+        };
+        let npm: Vec<i64> = self.version_times.values().cloned().collect();
+        let m = releases::summarize(&repo, &npm, Utc::now().timestamp());
+
+        let result = Self::calc_maintenance(
+            m.archived,
+            m.days_since_commit,
+            m.days_since_release,
+            m.releases_last_2_years,
+        );
+        debug!("maintenance_score: {:.2}", result);
+        self.explained(
+            "MAINTENANCE_SCORE",
+            json!(m),
+            "0 if archived, else 0.4 * 0.5^(days_since_commit / 180) \
+             + 0.3 * 0.5^(days_since_release / 365) + 0.3 * min(1, releases_last_2_years / 8); \
+             releases are npm versions, else GitHub releases, else tags",
+            result,
+        )
+    }
+
+    fn vulnerabilities(&self) -> f64 {
+        // advisories of the package in the local database
+        info!("calculating vulnerability_score");
+        let formula =
+            "1 - 0.5 * high - 0.25 * (moderate + unknown) - 0.1 * low over the unpatched \
+                       advisories, 0 with a critical one; without a known version, advisories \
+                       no release fixes are unpatched";
+        let db = match advisories::database() {
+            Some(db) => db,
+            None => {
+                return self.explained(
+                    "VULNERABILITY_SCORE",
+                    json!({ "database": null }),
+                    "1 without an advisory database",
+                    1.0,
+                )
+            }
+        };
+        let (ecosystem, name, version) = match self.package_identity() {
+            Some(p) => p,
+            None => {
+                return self.explained(
+                    "VULNERABILITY_SCORE",
+                    json!({ "package": null }),
+                    "1 without a package to look up",
+                    1.0,
+                )
+            }
+        };
+        let r = advisories::check(
+            db,
+            ecosystem,
+            &name,
+            version.as_deref(),
+            &self.version_times,
+        );
+        let severities: Vec<&str> = r.unpatched.iter().map(|u| u.severity.as_str()).collect();
+        let result = Self::calc_vulnerabilities(&severities);
+        debug!("vulnerability_score: {:.2}", result);
+        let mut inputs = json!(r);
+        inputs["ecosystem"] = json!(ecosystem);
+        inputs["package"] = json!(name);
+        inputs["version"] = json!(version);
+        self.explained("VULNERABILITY_SCORE", inputs, formula, result)
+    }
+
+    fn security(&self) -> f64 {
+        info!("calculating security_score");
+        let mut checks = Vec::new();
+
+        // files of the clone
+        if let Some(checkout) = self.checkout() {
+            let root = checkout.path();
+            let files = files::list(root);
+            let workflows = security::workflows(&files);
+            checks.push(security::security_policy(&files));
+            checks.push(security::dependency_update_tool(&files));
+            checks.push(security::pinned_actions(root, &workflows));
+            checks.push(security::dangerous_workflow(root, &workflows));
+            match security::signatures(root, 30) {
+                Ok((commits, tags)) => checks.extend([commits, tags]),
+                Err(e) => debug!("failed to read signatures: {}", e),
+            }
+        }
+
+        // protection of the default branch with the REST API
+        let q = format!(
+            "query {{ repository(owner: \"{}\", name: \"{}\") {{ defaultBranchRef {{ name }} }} }}",
+            self.owner, self.repo
+        );
+        let branch = self
+            .graph_json(json!({ "query": q }).to_string())
+            .ok()
+            .and_then(|r| {
+                r["data"]["repository"]["defaultBranchRef"]["name"]
+                    .as_str()
+                    .map(|b| b.to_string())
+            });
+        let protected = branch.as_ref().and_then(|b| {
+            self.rest_json(&format!("branches/{}", path_segment(b)))
+                .ok()
+                .and_then(|r| r["protected"].as_bool())
+        });
+        checks.push(security::Check::new(
+            "branch_protection",
+            protected,
+            &format!("default branch {}", branch.as_deref().unwrap_or("unknown")),
+        ));
+
+        let passed: Vec<Option<bool>> = checks.iter().map(|c| c.passed).collect();
+        let result = Self::calc_security(&passed);
+        debug!("security_score: {:.2}", result);
+        let inputs: serde_json::Map<String, serde_json::Value> = checks
+            .iter()
+            .map(|c| {
+                (
+                    c.name.to_string(),
+                    json!({ "passed": c.passed, "detail": c.detail }),
+                )
+            })
+            .collect();
+        *self.checks.borrow_mut() = checks;
+        self.explained(
+            "SECURITY_SCORE",
+            serde_json::Value::Object(inputs),
+            "passed / decided over security_policy, dependency_update_tool, pinned_actions, \
+             dangerous_workflow, signed_commits (last 30), signed_tags and branch_protection; \
+             0 if none could be decided",
+            result,
+        )
+    }
+
+    fn community(&self) -> f64 {
+        // community files of the clone and GitHub's community profile
+        info!("calculating community_score");
+        let files = self
+            .checkout()
+            .map(|c| files::list(c.path()))
+            .unwrap_or_default();
+        let profile = self.rest_json("community/profile").ok();
+        let health = profile
+            .as_ref()
+            .and_then(|p| p["health_percentage"].as_f64());
+        let from_profile = profile
+            .as_ref()
+            .map(community::profile_items)
+            .unwrap_or_default();
+
+        let mut inputs = serde_json::Map::new();
+        let mut items = BTreeMap::new();
+        for item in community::ITEMS {
+            let file = community::find(&files, item);
+            let present = file.is_some() || from_profile.contains(&item);
+            items.insert(item.to_string(), present);
+            inputs.insert(
+                item.to_string(),
+                json!({ "present": present, "file": file, "community_profile": from_profile.contains(&item) }),
+            );
+        }
+        inputs.insert("health_percentage".to_string(), json!(health));
+
+        let found = items.values().filter(|p| **p).count();
+        *self.community_items.borrow_mut() = items;
+
+        let result = Self::calc_community(found, community::ITEMS.len(), health);
+        debug!("community_score: {:.2}", result);
+        self.explained(
+            "COMMUNITY_SCORE",
+            serde_json::Value::Object(inputs),
+            "0.7 * present / 6 + 0.3 * health_percentage / 100 over contributing, code_of_conduct, \
+             issue_template, pull_request_template, governance and funding, present / 6 \
+             without the community profile",
+            result,
+        )
+    }
+
+    fn popularity(&self) -> f64 {
+        // repository counts with GraphQL API, downloads and dependents from the registry
+        info!("calculating popularity_score");
+        let mut p = popularity::Popularity::default();
+        if let Ok(r) = self.graph_json(popularity::query(&self.owner, &self.repo)) {
+            popularity::parse_repository(&r, &mut p);
+        }
+        if let Some((ecosystem, name, version)) = self.package_identity() {
+            popularity::registry(ecosystem, &name, version.as_deref(), &mut p);
+        }
+
+        let result = Self::calc_popularity(&p.signals());
+        debug!("popularity_score: {:.2}", result);
+        self.explained(
+            "POPULARITY_SCORE",
+            json!(p),
+            "mean of log10(1 + count) / log10(1 + reference), each at most 1, over the known \
+             counts: stars 10000, forks 1000, watchers 500, weekly_downloads 1000000, dependents 10000",
+            result,
+        )
+    }
+
+    fn security_checks(&self) -> Vec<Check> {
+        self.checks.borrow().clone()
+    }
+
+    fn community_items(&self) -> BTreeMap<String, bool> {
+        self.community_items.borrow().clone()
+    }
+
+    fn explain(&self) -> Explain {
+        self.explain.borrow().clone()
+    }
+
+    fn repository_url(&self) -> Option<String> {
+        Some(format!("https://github.com/{}/{}", self.owner, self.repo))
+    }
+
+    fn license_conflicts(&self) -> Vec<Conflict> {
+        self.conflicts.borrow().clone()
+    }
+
+    fn codebase(&self) -> Option<Codebase> {
+        self.profile().cloned()
+    }
+
+    fn target(&self) -> Target {
+        Target {
+            repository: self.repository_url(),
+            owner: Some(self.owner.clone()),
+            repo: Some(self.repo.clone()),
+            git_ref: self.git_ref.clone(),
+            package: self.package.clone(),
+            version: self.version.clone(),
+            clone: self.checkout().map(|c| c.path().to_path_buf()),
+            ..Default::default()
+        }
     }
 }
 
@@ -229,6 +1113,49 @@ impl Metrics for Github {
 mod tests {
     use super::*;
 
+    // testing parse_url()
+    #[test]
+    fn parse_url_without_ref() {
+        let (owner, repo, r) = parse_url("https://github.com/lee3445/ECE461_Team19_CLI").unwrap();
+        assert_eq!(owner, "lee3445");
+        assert_eq!(repo, "ECE461_Team19_CLI");
+        assert!(r.is_none());
+
+        let (_, _, r) = parse_url("https://github.com/lee3445/ECE461_Team19_CLI/issues").unwrap();
+        assert!(r.is_none());
+    }
+
+    #[test]
+    fn parse_url_with_ref() {
+        let (_, _, r) = parse_url("https://github.com/nodeca/js-yaml/tree/4.1.0").unwrap();
+        assert_eq!(r.as_deref(), Some("4.1.0"));
+
+        // a directory at the ref
+        let (_, _, r) = parse_url("https://github.com/nodeca/js-yaml/tree/4.1.0/lib/type").unwrap();
+        assert_eq!(r.as_deref(), Some("4.1.0"));
+
+        let (_, _, r) = parse_url("https://github.com/nodeca/js-yaml/commit/ee74ce4").unwrap();
+        assert_eq!(r.as_deref(), Some("ee74ce4"));
+
+        let (_, _, r) = parse_url("https://github.com/nodeca/js-yaml/releases/tag/v1.2.3").unwrap();
+        assert_eq!(r.as_deref(), Some("v1.2.3"));
+    }
+
+    #[test]
+    fn ref_query() {
+        assert_eq!(query_param("ref", "v1.0.0"), "ref=v1.0.0");
+        assert_eq!(query_param("ref", "v1.0+build"), "ref=v1.0%2Bbuild");
+        assert_eq!(query_param("ref", "a#b&c d"), "ref=a%23b%26c+d");
+    }
+
+    #[test]
+    fn branch_path() {
+        assert_eq!(path_segment("main"), "main");
+        assert_eq!(path_segment("a#b?c"), "a%23b%3Fc");
+        assert_eq!(path_segment("100%"), "100%25");
+        assert_eq!(path_segment("feature/x"), "feature%2Fx");
+    }
+
     // testing with_url()
     #[test]
     fn construct_with_url() {
@@ -327,8 +1254,11 @@ mod tests {
 
     #[test]
     fn bus_factor_normal_case() {
+        // a handful of people write most of the commits, a truck factor of
+        // 1 to 3, which scores at most 2 * 3 / 4 - 1 = 0.5
         let g = Github::with_url("https://github.com/EverestAPI/Olympus").unwrap();
-        assert!(g.bus_factor() > 0.5);
+        let score = g.bus_factor();
+        assert!((0.0..=0.5).contains(&score));
     }
 
     // testing responsiveness
@@ -347,8 +1277,14 @@ mod tests {
     // testing compatibility
     #[test]
     fn compatibility_no_license() {
+        // github doesn't detect a license, but LICENSE.txt holds the MIT text,
+        // which is now classified from the clone
         let g = Github::with_url("https://github.com/cloudinary/cloudinary_npm").unwrap();
-        assert!(g.compatibility() == 0.0);
+        assert!(g.compatibility() == 1.0);
+        assert_eq!(
+            g.explain()["LICENSE_SCORE"].inputs["detected"]["license"],
+            "MIT"
+        );
     }
 
     #[test]
@@ -0,0 +1,182 @@
+// commit authorship of a cloned repository
+use crate::metrics::bots;
+use git2::{Repository, Sort};
+use std::collections::HashMap;
+use std::path::Path;
+
+// author of commits, identified by email after applying .mailmap
+#[derive(Debug, Clone, PartialEq)]
+pub struct Author {
+    pub name: String,
+    pub email: String,
+    pub commits: usize,
+}
+
+// commits counted for the truck factor
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct History {
+    // most active first
+    pub authors: Vec<Author>,
+    pub bot_commits: usize,
+}
+
+impl History {
+    pub fn commits(&self) -> usize {
+        self.authors.iter().map(|a| a.commits).sum()
+    }
+}
+
+// authors of the non-merge commits reachable from HEAD
+// and at most `window_days` older than it
+pub fn authors(path: &Path, window_days: u32, accounts: &[String]) -> Result<History, git2::Error> {
+    let repo = Repository::open(path)?;
+    let mailmap = repo.mailmap()?;
+    let head = repo.head()?.peel_to_commit()?;
+    let since = head.time().seconds() - i64::from(window_days) * 24 * 60 * 60;
+
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TIME)?;
+    walk.push(head.id())?;
+
+    let mut history = History::default();
+    let mut counts: HashMap<String, Author> = HashMap::new();
+    for oid in walk {
+        let commit = repo.find_commit(oid?)?;
+        if commit.time().seconds() < since {
+            break;
+        }
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        let author = commit.author_with_mailmap(&mailmap)?;
+        let name = author.name().unwrap_or_default().to_string();
+        let email = author.email().unwrap_or_default().to_lowercase();
+        if bots::is_bot_identity(&name, &email, accounts) {
+            history.bot_commits += 1;
+            continue;
+        }
+        counts
+            .entry(email.clone())
+            .or_insert(Author {
+                name,
+                email,
+                commits: 0,
+            })
+            .commits += 1;
+    }
+
+    history.authors = counts.into_values().collect();
+    history
+        .authors
+        .sort_by(|a, b| b.commits.cmp(&a.commits).then(a.email.cmp(&b.email)));
+    Ok(history)
+}
+
+// branch checked out, None when HEAD is detached at a ref, and the commit id
+pub fn head(path: &Path) -> Result<(Option<String>, String), git2::Error> {
+    let repo = Repository::open(path)?;
+    let head = repo.head()?;
+    let branch = head
+        .is_branch()
+        .then(|| head.shorthand().map(|b| b.to_string()))
+        .flatten();
+    let id = head.peel_to_commit()?.id().to_string();
+    Ok((branch, id))
+}
+
+// smallest number of authors, most active first, whose commits
+// make up at least `share` of all commits; 0 without commits
+pub fn truck_factor(authors: &[Author], share: f64) -> usize {
+    let total: usize = authors.iter().map(|a| a.commits).sum();
+    let mut covered = 0;
+    for (i, a) in authors.iter().enumerate() {
+        if total == 0 {
+            break;
+        }
+        covered += a.commits;
+        if covered as f64 >= share * total as f64 {
+            return i + 1;
+        }
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Signature, Time};
+
+    fn author(email: &str, commits: usize) -> Author {
+        Author {
+            name: email.to_string(),
+            email: email.to_string(),
+            commits,
+        }
+    }
+
+    #[test]
+    fn truck_factor_cases() {
+        assert_eq!(truck_factor(&[], 0.5), 0);
+        assert_eq!(truck_factor(&[author("a", 10)], 0.5), 1);
+        assert_eq!(truck_factor(&[author("a", 6), author("b", 4)], 0.5), 1);
+        assert_eq!(
+            truck_factor(&[author("a", 4), author("b", 3), author("c", 3)], 0.5),
+            2
+        );
+        assert_eq!(
+            truck_factor(&[author("a", 1), author("b", 1), author("c", 1)], 1.0),
+            3
+        );
+    }
+
+    // commit with the given author, a day apart from `day` 0
+    fn commit(repo: &Repository, name: &str, email: &str, day: i64) {
+        let sig = Signature::new(name, email, &Time::new(1_600_000_000 + day * 86400, 0)).unwrap();
+        let tree = {
+            let mut index = repo.index().unwrap();
+            repo.find_tree(index.write_tree().unwrap()).unwrap()
+        };
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, "change", &tree, &parents)
+            .unwrap();
+    }
+
+    #[test]
+    fn authors_from_history() {
+        let dir = std::env::temp_dir().join(format!("history_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        std::fs::write(
+            dir.join(".mailmap"),
+            "Alice <alice@example.com> <alice@old.example.com>\n",
+        )
+        .unwrap();
+
+        commit(&repo, "Old", "old@example.com", 0);
+        commit(&repo, "Alice", "alice@old.example.com", 500);
+        commit(&repo, "Alice", "alice@example.com", 501);
+        commit(&repo, "Bob", "bob@example.com", 502);
+        commit(
+            &repo,
+            "dependabot[bot]",
+            "bot@users.noreply.github.com",
+            503,
+        );
+        commit(&repo, "Alice", "ALICE@example.com", 504);
+
+        let history = authors(&dir, 365, &[]).unwrap();
+        let (branch, id) = head(&dir).unwrap();
+        assert!(branch.is_some());
+        assert_eq!(id.len(), 40);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // the commit of day 0 is out of the window
+        assert_eq!(history.commits(), 4);
+        assert_eq!(history.bot_commits, 1);
+        assert_eq!(history.authors[0].email, "alice@example.com");
+        assert_eq!(history.authors[0].commits, 3);
+        assert_eq!(history.authors[1].email, "bob@example.com");
+        assert_eq!(truck_factor(&history.authors, 0.5), 1);
+    }
+}
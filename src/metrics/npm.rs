@@ -1,16 +1,61 @@
+use crate::config;
+use crate::license::deps::{self, Dependency, Ecosystem};
 use crate::metrics::github::Github;
-use crate::metrics::Metrics;
+use crate::metrics::releases;
 use serde_json::Value; // for parsing json
 
-pub struct Npm {
-    gh: Box<dyn Metrics>,
+// packages are evaluated from the repository they were published from
+pub struct Npm;
+
+// extract (package name, version) from an npm url
+// versions come from paths like /package/<name>/v/<version>
+pub fn parse_url(url: &str) -> Option<(String, Option<String>)> {
+    let path = url.strip_prefix("https://www.npmjs.com/package/")?;
+    let (name, version) = match path.split_once("/v/") {
+        Some((n, v)) => (n, Some(v.trim_end_matches('/').to_string())),
+        None => (path.trim_end_matches('/'), None),
+    };
+    if name.is_empty() {
+        return None;
+    }
+    Some((name.to_string(), version))
+}
+
+// license field of package metadata
+// old packages use {"type": "MIT"} or a list of such objects instead of an SPDX expression
+pub fn declared_license(metadata: &Value) -> Option<String> {
+    match &metadata["license"] {
+        Value::String(l) => Some(l.clone()),
+        Value::Object(o) => o.get("type")?.as_str().map(|l| l.to_string()),
+        _ => {
+            let types: Vec<&str> = metadata["licenses"]
+                .as_array()?
+                .iter()
+                .filter_map(|l| l["type"].as_str())
+                .collect();
+            if types.is_empty() {
+                None
+            } else {
+                Some(types.join(" OR "))
+            }
+        }
+    }
+}
+
+// dependencies of a version in package metadata, None when the registry
+// doesn't know the version, so the repository's manifest is used instead
+pub fn published_dependencies(metadata: &Value, version: &str) -> Option<Vec<Dependency>> {
+    let published = metadata["versions"].get(version)?;
+    Some(deps::npm_dependencies(published))
 }
 
 impl Npm {
-    pub fn with_url(url: &str) -> Option<Npm> {
-        let npm_url = url.replace(
-            "https://www.npmjs.com/package/",
-            "https://registry.npmjs.org/",
+    pub fn with_url(url: &str) -> Option<Github> {
+        let (name, version) = parse_url(url)?;
+        let npm_url = format!(
+            "{}/{}",
+            config::get().npm.registry_url.trim_end_matches('/'),
+            name
         );
 
         let npm_url = reqwest::blocking::get(npm_url).ok()?.text().ok()?;
@@ -27,48 +72,99 @@ impl Npm {
             .and_then(|value| value.get("url"))
             .and_then(|value| value.as_str());
 
-        // dereference the url so we can use .replace() later
-        let derefurl = &giturl.as_deref()?;
-
         // Do not need to check if url contains git+, just do replace. That would take care of it
-        let derefurl = derefurl.replace("git+", "");
+        let derefurl = giturl?.replace("git+", "");
         let derefurl = derefurl.replace(".git", "");
 
         // create github object
-        let output = Github::with_url(&derefurl)?;
+        let mut output = Github::with_url(&derefurl)?.for_package(&name);
+
+        // evaluate the published commit of the requested version,
+        // falling back to the conventional tag name
+        if let Some(v) = &version {
+            let head = root["versions"][v]["gitHead"].as_str();
+            output = match head {
+                Some(h) => output.at_ref(h),
+                None => output.at_ref(&format!("v{}", v)),
+            };
+        }
+
+        // license expression of the requested or latest version
+        let v = version
+            .as_deref()
+            .or(root["dist-tags"]["latest"].as_str())
+            .unwrap_or_default();
+        if !v.is_empty() {
+            output = output.with_version(v);
+        }
+        if let Some(l) = declared_license(&root["versions"][v]).or(declared_license(&root)) {
+            output = output.with_declared_license(&l);
+        }
+
+        // dependencies of the published version rather than the repository's
+        if let Some(d) = published_dependencies(&root, v) {
+            output = output.with_dependencies(Ecosystem::Npm, d);
+        }
+
+        // versions are released when published to the registry
+        output = output.with_version_times(releases::npm_release_times(&root["time"]));
 
         // return
-        Some(Npm {
-            gh: Box::new(output),
-        })
+        Some(output)
     }
 }
 
-impl Metrics for Npm {
-    fn ramp_up_time(&self) -> f64 {
-        self.gh.ramp_up_time()
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
 
-    fn correctness(&self) -> f64 {
-        self.gh.correctness()
+    #[test]
+    fn parse_package_url() {
+        let (name, version) = parse_url("https://www.npmjs.com/package/js-yaml").unwrap();
+        assert_eq!(name, "js-yaml");
+        assert!(version.is_none());
+
+        let (name, version) =
+            parse_url("https://www.npmjs.com/package/@types/node/v/18.11.9").unwrap();
+        assert_eq!(name, "@types/node");
+        assert_eq!(version.as_deref(), Some("18.11.9"));
+
+        assert!(parse_url("https://www.npmjs.com/package/").is_none());
+        assert!(parse_url("https://www.youtube.com/").is_none());
     }
 
-    fn bus_factor(&self) -> f64 {
-        self.gh.bus_factor()
-    }
+    #[test]
+    fn declared_licenses() {
+        let m = serde_json::json!({ "license": "MIT OR Apache-2.0" });
+        assert_eq!(declared_license(&m).as_deref(), Some("MIT OR Apache-2.0"));
 
-    fn responsiveness(&self) -> f64 {
-        self.gh.responsiveness()
-    }
+        let m = serde_json::json!({ "license": { "type": "ISC" } });
+        assert_eq!(declared_license(&m).as_deref(), Some("ISC"));
+
+        let m = serde_json::json!({ "licenses": [{ "type": "MIT" }, { "type": "GPL-2.0" }] });
+        assert_eq!(declared_license(&m).as_deref(), Some("MIT OR GPL-2.0"));
 
-    fn compatibility(&self) -> f64 {
-        self.gh.compatibility()
+        assert!(declared_license(&serde_json::json!({})).is_none());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn published_dependency_lists() {
+        let metadata = serde_json::json!({
+            "versions": {
+                "1.0.0": { "dependencies": { "a": "^1.0.0" } },
+                "2.0.0": {}
+            }
+        });
+        let deps = published_dependencies(&metadata, "1.0.0").unwrap();
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps[0].name, "a");
+        assert!(published_dependencies(&metadata, "2.0.0")
+            .unwrap()
+            .is_empty());
+        assert!(published_dependencies(&metadata, "3.0.0").is_none());
+        assert!(published_dependencies(&metadata, "").is_none());
+    }
 
     #[test]
     fn no_url() {
@@ -0,0 +1,166 @@
+// how tightly dependency requirements constrain the versions used
+use crate::license::deps::{Dependency, Ecosystem};
+
+// version as far as it is given, e.g. "1.2" is (1, Some(2), None)
+// x, X and * stand for any number and end the version
+type Partial = (u64, Option<u64>, Option<u64>);
+
+fn partial(v: &str) -> Option<Partial> {
+    let v = v.trim().trim_start_matches(['v', '=']);
+    // pre-release and build metadata don't change the major.minor
+    let v = v.split(['-', '+']).next()?;
+    let mut parts = v.split('.').map(|p| match p {
+        "x" | "X" | "*" => Ok(None),
+        p => p.parse::<u64>().map(Some),
+    });
+    let major = parts.next()?.ok()??;
+    let minor = parts.next().unwrap_or(Ok(None)).ok()?;
+    let patch = match minor {
+        Some(_) => parts.next().unwrap_or(Ok(None)).ok()?,
+        None => None,
+    };
+    Some((major, minor, patch))
+}
+
+// major.minor a comparator set allows, if it allows exactly one
+// `bare` is how a version without operator is read: exact for npm, caret for cargo
+fn pinned_to(set: &str, bare_is_caret: bool) -> Option<(u64, u64)> {
+    // npm hyphen range "1.2.0 - 1.2.9"
+    if let Some((lo, hi)) = set.split_once(" - ") {
+        let (lo, hi) = (partial(lo)?, partial(hi)?);
+        return (lo.0 == hi.0 && lo.1.is_some() && lo.1 == hi.1).then(|| (lo.0, lo.1.unwrap()));
+    }
+
+    let comparators: Vec<&str> = set
+        .split([' ', ','])
+        .map(|c| c.trim())
+        .filter(|c| !c.is_empty())
+        .collect();
+    let mut lower: Option<(u64, u64)> = None;
+    let mut upper_ok = false;
+    for c in &comparators {
+        let (op, v) = match c.find(|ch: char| ch.is_ascii_digit()) {
+            Some(i) => c.split_at(i),
+            None => return None,
+        };
+        let (major, minor, patch) = partial(v)?;
+        match op.trim() {
+            "" | "=" | "v" if !(bare_is_caret && op.trim().is_empty()) => {
+                return Some((major, minor?));
+            }
+            "" | "^" => {
+                // ^0.2.3 allows 0.2.x only, ^0.0.3 only 0.0.3
+                return (major == 0).then_some((major, minor?));
+            }
+            "~" | "~>" => return Some((major, minor?)),
+            ">=" => lower = Some((major, minor?)),
+            ">" => lower = Some((major, minor?)),
+            "<" => {
+                // < 1.3.0 after >= 1.2.0
+                upper_ok = match lower {
+                    Some((lm, ln)) => {
+                        (major == lm && minor == Some(ln + 1) && patch.unwrap_or(0) == 0)
+                            || (major == lm && minor == Some(ln) && patch.is_some())
+                    }
+                    None => false,
+                }
+            }
+            "<=" => upper_ok = lower.is_some_and(|(lm, ln)| major == lm && minor == Some(ln)),
+            _ => return None,
+        }
+    }
+    if upper_ok {
+        lower
+    } else {
+        None
+    }
+}
+
+// whether a requirement only allows versions of one major.minor
+pub fn is_pinned(requirement: &str, ecosystem: Ecosystem) -> bool {
+    let bare_is_caret = ecosystem == Ecosystem::Crates;
+    let alternatives: Vec<Option<(u64, u64)>> = requirement
+        .split("||")
+        .map(|set| pinned_to(set.trim(), bare_is_caret))
+        .collect();
+    match alternatives.first() {
+        Some(Some(first)) => alternatives.iter().all(|a| *a == Some(*first)),
+        _ => false,
+    }
+}
+
+// dependencies whose requirements are not pinned
+pub fn unpinned(deps: &[Dependency], ecosystem: Ecosystem) -> Vec<&Dependency> {
+    deps.iter()
+        .filter(|d| !is_pinned(&d.requirement, ecosystem))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn npm_requirements() {
+        for r in [
+            "1.2.3",
+            "=1.2.3",
+            "v1.2.3",
+            "1.2",
+            "1.2.x",
+            "~1.2.3",
+            "~1.2",
+            "^0.2.3",
+            "^0.0.3",
+            ">=1.2.0 <1.3.0",
+            ">=1.2.3 <1.2.9",
+            ">=1.2.0 <=1.2.9",
+            "1.2.0 - 1.2.9",
+            "~1.2.0 || 1.2.5",
+            "1.2.3-beta.1",
+        ] {
+            assert!(is_pinned(r, Ecosystem::Npm), "{}", r);
+        }
+        for r in [
+            "^1.2.3",
+            "~1",
+            "1",
+            "1.x",
+            "*",
+            "",
+            "latest",
+            ">=1.2.0",
+            ">=1.2.0 <2.0.0",
+            "1.2.0 - 1.4.0",
+            "~1.2.0 || ~1.3.0",
+            "git+https://github.com/a/b.git",
+            "file:../local",
+        ] {
+            assert!(!is_pinned(r, Ecosystem::Npm), "{}", r);
+        }
+    }
+
+    #[test]
+    fn cargo_requirements() {
+        for r in ["=1.2.3", "~1.2", "0.2", "0.2.3", "^0.4", ">=1.2.0, <1.3.0"] {
+            assert!(is_pinned(r, Ecosystem::Crates), "{}", r);
+        }
+        for r in ["1.2.3", "1", "^1.2", "*", ">=1.2"] {
+            assert!(!is_pinned(r, Ecosystem::Crates), "{}", r);
+        }
+    }
+
+    #[test]
+    fn unpinned_dependencies() {
+        let d = |name: &str, req: &str| Dependency {
+            name: name.to_string(),
+            requirement: req.to_string(),
+        };
+        let deps = [d("a", "~1.2.0"), d("b", "^2.0.0"), d("c", "0.4.1")];
+        let names: Vec<&str> = unpinned(&deps, Ecosystem::Npm)
+            .iter()
+            .map(|d| d.name.as_str())
+            .collect();
+        assert_eq!(names, ["b"]);
+    }
+}
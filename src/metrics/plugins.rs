@@ -0,0 +1,218 @@
+// custom metrics scored outside of the CLI
+//
+// a command plugin reads the module to score as one JSON object on stdin, e.g.
+// {"url": "https://www.npmjs.com/package/x", "repository": "https://github.com/o/x",
+//  "owner": "o", "repo": "x", "ref": null, "package": "x", "version": "1.0.0",
+//  "clone": "/tmp/ece461_team19_cli/cloned_o_x_1_0"}
+// and writes its result as one JSON object on stdout, e.g.
+// {"score": 1.0, "inputs": {"vendor": "o"}, "formula": "1 if the owner is approved"}
+use crate::config::PluginConfig;
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command as Process, Stdio};
+use std::time::{Duration, Instant};
+
+// module handed to plugins, fields are null when unknown
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Target {
+    pub url: String,
+    pub repository: Option<String>,
+    pub owner: Option<String>,
+    pub repo: Option<String>,
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+    pub package: Option<String>,
+    pub version: Option<String>,
+    // local clone, removed once the module is scored
+    pub clone: Option<PathBuf>,
+}
+
+// score in [0, 1] and how the plugin arrived at it
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Outcome {
+    pub score: f64,
+    #[serde(default)]
+    pub inputs: Value,
+    #[serde(default)]
+    pub formula: String,
+}
+
+// custom metric, scored once per module
+pub trait Plugin: Send + Sync {
+    fn name(&self) -> &str;
+    fn score(&self, target: &Target) -> Result<Outcome, String>;
+}
+
+// output name of the score of a plugin, e.g. APPROVED_VENDOR_SCORE
+pub fn column(name: &str) -> String {
+    format!("{}_SCORE", name.to_uppercase())
+}
+
+// plugin running an external command
+pub struct Command {
+    config: PluginConfig,
+}
+
+impl Command {
+    pub fn new(config: PluginConfig) -> Command {
+        Command { config }
+    }
+
+    // stdout of the command given `input` on stdin
+    fn run(&self, input: &str) -> Result<String, String> {
+        let (program, args) = self
+            .config
+            .command
+            .split_first()
+            .ok_or("empty command".to_string())?;
+        let mut child = Process::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("{}: {}", program, e))?;
+
+        // a plugin doesn't have to read its input
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(input.as_bytes());
+        }
+        let read = |mut pipe: Box<dyn Read + Send>| {
+            std::thread::spawn(move || {
+                let mut s = String::new();
+                let _ = pipe.read_to_string(&mut s);
+                s
+            })
+        };
+        let stdout = read(Box::new(child.stdout.take().unwrap()));
+        let stderr = read(Box::new(child.stderr.take().unwrap()));
+
+        let deadline = Instant::now() + Duration::from_secs(self.config.timeout_secs);
+        let status = loop {
+            if let Some(status) = child.try_wait().map_err(|e| format!("{}", e))? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!(
+                    "timed out after {} seconds",
+                    self.config.timeout_secs
+                ));
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        let stdout = stdout.join().unwrap_or_default();
+        if !status.success() {
+            let stderr = stderr.join().unwrap_or_default();
+            return Err(format!("{}: {}", status, stderr.trim()));
+        }
+        Ok(stdout)
+    }
+}
+
+impl Plugin for Command {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn score(&self, target: &Target) -> Result<Outcome, String> {
+        let input = serde_json::to_string(target).map_err(|e| format!("{}", e))?;
+        let output = self.run(&input)?;
+        let outcome: Outcome =
+            serde_json::from_str(output.trim()).map_err(|e| format!("bad output: {}", e))?;
+        if !(0.0..=1.0).contains(&outcome.score) {
+            return Err(format!("score {} is not in [0, 1]", outcome.score));
+        }
+        Ok(outcome)
+    }
+}
+
+// plugins of the configuration, in order
+pub fn registry(configs: &[PluginConfig]) -> Vec<Box<dyn Plugin>> {
+    configs
+        .iter()
+        .map(|c| {
+            info!("registering plugin {}", c.name);
+            Box::new(Command::new(c.clone())) as Box<dyn Plugin>
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell(script: &str, timeout_secs: u64) -> Command {
+        Command::new(PluginConfig {
+            name: "test".to_string(),
+            command: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+            timeout_secs,
+        })
+    }
+
+    fn target() -> Target {
+        Target {
+            url: "https://www.npmjs.com/package/left-pad".to_string(),
+            owner: Some("stevemao".to_string()),
+            package: Some("left-pad".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn target_json() {
+        let v = serde_json::to_value(target()).unwrap();
+        assert_eq!(v["owner"], "stevemao");
+        assert_eq!(v["ref"], Value::Null);
+        assert_eq!(v["clone"], Value::Null);
+    }
+
+    #[test]
+    fn scores() {
+        // the plugin decides from its input
+        let p = shell(
+            "read -r t; case \"$t\" in \
+             *stevemao*) echo '{\"score\": 1, \"inputs\": {\"vendor\": \"stevemao\"}, \"formula\": \"approved\"}';; \
+             *) echo '{\"score\": 0}';; esac",
+            5,
+        );
+        let o = p.score(&target()).unwrap();
+        assert_eq!(o.score, 1.0);
+        assert_eq!(o.inputs["vendor"], "stevemao");
+        assert_eq!(o.formula, "approved");
+        let o = p.score(&Target::default()).unwrap();
+        assert_eq!(o.score, 0.0);
+        assert_eq!(o.inputs, Value::Null);
+    }
+
+    #[test]
+    fn failures() {
+        let err = shell("echo broken >&2; exit 3", 5)
+            .score(&target())
+            .unwrap_err();
+        assert!(err.contains("broken"));
+        assert!(shell("echo not json", 5)
+            .score(&target())
+            .unwrap_err()
+            .contains("bad output"));
+        assert!(shell("echo '{\"score\": 2}'", 5)
+            .score(&target())
+            .unwrap_err()
+            .contains("not in [0, 1]"));
+        assert!(shell("sleep 5", 1)
+            .score(&target())
+            .unwrap_err()
+            .contains("timed out"));
+
+        let missing = Command::new(PluginConfig {
+            name: "missing".to_string(),
+            command: vec!["./no-such-plugin".to_string()],
+            timeout_secs: 1,
+        });
+        assert!(missing.score(&target()).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+// how widely a package is used
+use crate::config;
+use serde::Serialize;
+use serde_json::Value;
+
+// counts reaching these values score 1 on their own
+pub const STARS: f64 = 10_000.0;
+pub const FORKS: f64 = 1_000.0;
+pub const WATCHERS: f64 = 500.0;
+pub const WEEKLY_DOWNLOADS: f64 = 1_000_000.0;
+pub const DEPENDENTS: f64 = 10_000.0;
+
+// GraphQL query for the star, fork and watcher counts
+pub fn query(owner: &str, repo: &str) -> String {
+    let q = format!(
+        "query {{ repository(owner: \"{}\", name: \"{}\") {{ stargazerCount forkCount watchers {{ totalCount }} }} }}",
+        owner, repo
+    );
+    serde_json::json!({ "query": q }).to_string()
+}
+
+// adoption signals, None when unknown
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Popularity {
+    pub stars: Option<u64>,
+    pub forks: Option<u64>,
+    pub watchers: Option<u64>,
+    pub weekly_downloads: Option<u64>,
+    pub dependents: Option<u64>,
+}
+
+impl Popularity {
+    // (count, reference) of the known signals
+    pub fn signals(&self) -> Vec<(f64, f64)> {
+        [
+            (self.stars, STARS),
+            (self.forks, FORKS),
+            (self.watchers, WATCHERS),
+            (self.weekly_downloads, WEEKLY_DOWNLOADS),
+            (self.dependents, DEPENDENTS),
+        ]
+        .into_iter()
+        .filter_map(|(c, r)| Some((c? as f64, r)))
+        .collect()
+    }
+}
+
+pub fn parse_repository(response: &Value, p: &mut Popularity) {
+    let repo = &response["data"]["repository"];
+    p.stars = repo["stargazerCount"].as_u64();
+    p.forks = repo["forkCount"].as_u64();
+    p.watchers = repo["watchers"]["totalCount"].as_u64();
+}
+
+// json of a registry API, which wants a user agent but no GitHub token
+fn get_json(url: &str) -> Option<Value> {
+    reqwest::blocking::Client::builder()
+        .user_agent("ECE461_Team19_CLI")
+        .build()
+        .ok()?
+        .get(url)
+        .send()
+        .ok()?
+        .json()
+        .ok()
+}
+
+// downloads last week and dependents of a package in its registry
+pub fn registry(ecosystem: &str, name: &str, version: Option<&str>, p: &mut Popularity) {
+    let c = config::get();
+    match ecosystem {
+        "npm" => {
+            let downloads = c.npm.downloads_url.trim_end_matches('/');
+            p.weekly_downloads = get_json(&format!("{}/point/last-week/{}", downloads, name))
+                .and_then(|d| d["downloads"].as_u64());
+            // dependents are known per version
+            if let Some(v) = version {
+                let url = format!(
+                    "{}/systems/npm/packages/{}/versions/{}:dependents",
+                    c.npm.dependents_url.trim_end_matches('/'),
+                    name.replace('/', "%2F"),
+                    v
+                );
+                p.dependents = get_json(&url).and_then(|d| d["dependentCount"].as_u64());
+            }
+        }
+        "crates.io" => {
+            let api = c.crates.api_url.trim_end_matches('/');
+            // crates.io counts the last 90 days
+            p.weekly_downloads = get_json(&format!("{}/crates/{}", api, name))
+                .and_then(|d| d["crate"]["recent_downloads"].as_u64())
+                .map(|d| d * 7 / 90);
+            p.dependents = get_json(&format!(
+                "{}/crates/{}/reverse_dependencies?per_page=1",
+                api, name
+            ))
+            .and_then(|d| d["meta"]["total"].as_u64());
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn repository_counts() {
+        let mut p = Popularity::default();
+        parse_repository(
+            &json!({ "data": { "repository": {
+                "stargazerCount": 120, "forkCount": 30, "watchers": { "totalCount": 8 }
+            } } }),
+            &mut p,
+        );
+        assert_eq!(p.stars, Some(120));
+        assert_eq!(p.forks, Some(30));
+        assert_eq!(p.watchers, Some(8));
+        assert_eq!(
+            p.signals(),
+            [(120.0, STARS), (30.0, FORKS), (8.0, WATCHERS)]
+        );
+    }
+}
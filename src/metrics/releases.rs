@@ -0,0 +1,179 @@
+// release history and recent activity of a repository
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+const DAY: f64 = 86400.0;
+
+// GraphQL query for the archive state, last commit on the default branch,
+// and the latest releases and tags
+pub fn query(owner: &str, repo: &str) -> String {
+    let q = format!(
+        "query {{ repository(owner: \"{}\", name: \"{}\") {{ isArchived \
+         defaultBranchRef {{ target {{ ... on Commit {{ committedDate }} }} }} \
+         releases(first: 100, orderBy: {{ field: CREATED_AT, direction: DESC }}) {{ nodes {{ publishedAt isDraft }} }} \
+         refs(refPrefix: \"refs/tags/\", first: 100, orderBy: {{ field: TAG_COMMIT_DATE, direction: DESC }}) {{ nodes {{ \
+         target {{ ... on Commit {{ committedDate }} ... on Tag {{ tagger {{ date }} }} }} }} }} }} }}",
+        owner, repo
+    );
+    serde_json::json!({ "query": q }).to_string()
+}
+
+fn timestamp(v: &Value) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(v.as_str()?)
+        .ok()
+        .map(|t| t.timestamp())
+}
+
+// what the repository reports about itself, times in seconds since the epoch
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Repository {
+    pub archived: bool,
+    pub last_commit: Option<i64>,
+    pub releases: Vec<i64>,
+    pub tags: Vec<i64>,
+}
+
+pub fn parse(response: &Value) -> Option<Repository> {
+    let repo = &response["data"]["repository"];
+    let archived = repo["isArchived"].as_bool()?;
+    let releases = repo["releases"]["nodes"]
+        .as_array()
+        .map(|n| {
+            n.iter()
+                .filter(|r| r["isDraft"].as_bool() != Some(true))
+                .filter_map(|r| timestamp(&r["publishedAt"]))
+                .collect()
+        })
+        .unwrap_or_default();
+    // lightweight tags point at commits, annotated tags have their own date
+    let tags = repo["refs"]["nodes"]
+        .as_array()
+        .map(|n| {
+            n.iter()
+                .filter_map(|t| {
+                    timestamp(&t["target"]["committedDate"])
+                        .or(timestamp(&t["target"]["tagger"]["date"]))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(Repository {
+        archived,
+        last_commit: timestamp(&repo["defaultBranchRef"]["target"]["committedDate"]),
+        releases,
+        tags,
+    })
+}
+
+// publish time of each version in the `time` field of npm registry metadata
+pub fn npm_release_times(time: &Value) -> BTreeMap<String, i64> {
+    time.as_object()
+        .map(|o| {
+            o.iter()
+                .filter(|(k, _)| *k != "created" && *k != "modified")
+                .filter_map(|(k, v)| Some((k.clone(), timestamp(v)?)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// activity used by the maintenance metric
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Maintenance {
+    pub archived: bool,
+    // where release dates came from: npm, releases or tags
+    pub release_source: Option<&'static str>,
+    pub days_since_release: Option<f64>,
+    pub releases_last_2_years: usize,
+    pub days_since_commit: Option<f64>,
+}
+
+// npm publish times are preferred over GitHub releases, and those over tags
+pub fn summarize(repo: &Repository, npm: &[i64], now: i64) -> Maintenance {
+    let (source, dates) = [
+        ("npm", npm),
+        ("releases", &repo.releases),
+        ("tags", &repo.tags),
+    ]
+    .into_iter()
+    .find(|(_, d)| !d.is_empty())
+    .map(|(s, d)| (Some(s), d))
+    .unwrap_or((None, &[]));
+    let days = |t: i64| (now - t).max(0) as f64 / DAY;
+    Maintenance {
+        archived: repo.archived,
+        release_source: source,
+        days_since_release: dates.iter().max().map(|t| days(*t)),
+        releases_last_2_years: dates.iter().filter(|t| days(**t) <= 730.0).count(),
+        days_since_commit: repo.last_commit.map(days),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_repository() {
+        let response = json!({ "data": { "repository": {
+            "isArchived": false,
+            "defaultBranchRef": { "target": { "committedDate": "2023-01-02T00:00:00Z" } },
+            "releases": { "nodes": [
+                { "publishedAt": "2023-01-01T00:00:00Z", "isDraft": false },
+                { "publishedAt": null, "isDraft": true }
+            ] },
+            "refs": { "nodes": [
+                { "target": { "committedDate": "2022-12-01T00:00:00Z" } },
+                { "target": { "tagger": { "date": "2022-11-01T00:00:00+01:00" } } }
+            ] }
+        } } });
+        let repo = parse(&response).unwrap();
+        assert!(!repo.archived);
+        assert_eq!(repo.last_commit, Some(1672617600));
+        assert_eq!(repo.releases, [1672531200]);
+        assert_eq!(repo.tags.len(), 2);
+        assert!(parse(&json!({ "data": { "repository": null } })).is_none());
+    }
+
+    #[test]
+    fn npm_times() {
+        let time = json!({
+            "created": "2020-01-01T00:00:00.000Z",
+            "modified": "2023-01-01T00:00:00.000Z",
+            "1.0.0": "2020-01-01T00:00:00.000Z",
+            "1.1.0": "2021-01-01T00:00:00.000Z"
+        });
+        let times = npm_release_times(&time);
+        assert_eq!(times.len(), 2);
+        assert_eq!(times["1.1.0"], 1609459200);
+        assert!(npm_release_times(&json!(null)).is_empty());
+    }
+
+    #[test]
+    fn summary_sources() {
+        let day = DAY as i64;
+        let now = 1000 * day;
+        let repo = Repository {
+            archived: true,
+            last_commit: Some(now - 10 * day),
+            releases: vec![now - 100 * day, now - 800 * day],
+            tags: vec![now - day],
+        };
+        let m = summarize(&repo, &[], now);
+        assert_eq!(m.release_source, Some("releases"));
+        assert_eq!(m.days_since_release, Some(100.0));
+        assert_eq!(m.releases_last_2_years, 1);
+        assert_eq!(m.days_since_commit, Some(10.0));
+        assert!(m.archived);
+
+        let m = summarize(&repo, &[now - 5 * day], now);
+        assert_eq!(m.release_source, Some("npm"));
+        assert_eq!(m.days_since_release, Some(5.0));
+
+        let m = summarize(&Repository::default(), &[], now);
+        assert_eq!(m.release_source, None);
+        assert_eq!(m.days_since_release, None);
+    }
+}
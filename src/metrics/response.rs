@@ -0,0 +1,254 @@
+// how quickly maintainers answer and close issues and pull requests
+use crate::metrics::bots;
+use serde::Serialize;
+use serde_json::Value;
+
+// associations of people who can act for the repository
+const MAINTAINERS: [&str; 3] = ["OWNER", "MEMBER", "COLLABORATOR"];
+
+// search nodes requested by `query`, an issue or a pull request each
+const NODE_FIELDS: &str = "createdAt closedAt authorAssociation author { login __typename } \
+     comments(first: 20) { nodes { createdAt authorAssociation author { login __typename } } }";
+
+// issue or pull request, times in seconds since the epoch
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub created: i64,
+    pub closed: Option<i64>,
+    // first comment or review of a maintainer other than the author, or closing
+    pub first_response: Option<i64>,
+    // opened by a maintainer, who doesn't wait for a response
+    pub by_maintainer: bool,
+    // opened by an automated account, e.g. a dependency update
+    pub by_bot: bool,
+}
+
+// GraphQL search of the `first` most recent issues or pull requests
+// created since `since` (YYYY-MM-DD)
+pub fn query(owner: &str, repo: &str, kind: &str, since: &str, first: u32) -> String {
+    let reviews = if kind == "pr" {
+        "reviews(first: 20) { nodes { createdAt authorAssociation author { login __typename } } }"
+    } else {
+        ""
+    };
+    let q = format!(
+        "query {{ search(query: \"repo:{}/{} is:{} created:>={} sort:created-desc\", type: ISSUE, first: {}) {{ \
+         issueCount nodes {{ \
+         ... on Issue {{ {} }} \
+         ... on PullRequest {{ {} {} }} }} }} }}",
+        owner, repo, kind, since, first, NODE_FIELDS, NODE_FIELDS, reviews
+    );
+    serde_json::json!({ "query": q }).to_string()
+}
+
+fn timestamp(v: &Value) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(v.as_str()?)
+        .ok()
+        .map(|t| t.timestamp())
+}
+
+// items of a search response, bots in `accounts` besides GitHub apps
+pub fn items(response: &Value, accounts: &[String]) -> Vec<Item> {
+    let nodes = match response["data"]["search"]["nodes"].as_array() {
+        Some(n) => n,
+        None => return Vec::new(),
+    };
+    nodes
+        .iter()
+        .filter_map(|n| {
+            let created = timestamp(&n["createdAt"])?;
+            let closed = timestamp(&n["closedAt"]);
+            let author = n["author"]["login"].as_str();
+            let by_maintainer = n["authorAssociation"]
+                .as_str()
+                .is_some_and(|a| MAINTAINERS.contains(&a));
+            let by_bot = bots::is_bot_user(&n["author"], accounts);
+
+            // comments of bots, e.g. stale or CI reports, are no response
+            let responses = ["comments", "reviews"]
+                .iter()
+                .filter_map(|k| n[k]["nodes"].as_array())
+                .flatten()
+                .filter(|c| {
+                    c["authorAssociation"]
+                        .as_str()
+                        .is_some_and(|a| MAINTAINERS.contains(&a))
+                        && c["author"]["login"].as_str() != author
+                        && !bots::is_bot_user(&c["author"], accounts)
+                })
+                .filter_map(|c| timestamp(&c["createdAt"]));
+            let first_response = responses.chain(closed).min();
+
+            Some(Item {
+                created,
+                closed,
+                first_response,
+                by_maintainer,
+                by_bot,
+            })
+        })
+        .collect()
+}
+
+// p-th percentile (0 to 100) by linear interpolation, None when empty
+pub fn percentile(values: &[f64], p: f64) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+    Some(sorted[lo] + (sorted[hi] - sorted[lo]) * (rank - lo as f64))
+}
+
+// response and close times in days, without the items opened by bots
+// unanswered and open items count with the time waited so far
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Summary {
+    pub items: usize,
+    pub bot_items: usize,
+    pub answered: usize,
+    pub closed: usize,
+    pub response_median_days: Option<f64>,
+    pub response_p90_days: Option<f64>,
+    pub close_median_days: Option<f64>,
+    pub close_p90_days: Option<f64>,
+}
+
+pub fn summarize(all: &[Item], now: i64) -> Summary {
+    let items: Vec<&Item> = all.iter().filter(|i| !i.by_bot).collect();
+    let days = |from: i64, to: Option<i64>| (to.unwrap_or(now) - from).max(0) as f64 / 86400.0;
+    let response: Vec<f64> = items
+        .iter()
+        .filter(|i| !i.by_maintainer)
+        .map(|i| days(i.created, i.first_response))
+        .collect();
+    let close: Vec<f64> = items.iter().map(|i| days(i.created, i.closed)).collect();
+    Summary {
+        items: items.len(),
+        bot_items: all.len() - items.len(),
+        answered: items
+            .iter()
+            .filter(|i| !i.by_maintainer && i.first_response.is_some())
+            .count(),
+        closed: items.iter().filter(|i| i.closed.is_some()).count(),
+        response_median_days: percentile(&response, 50.0),
+        response_p90_days: percentile(&response, 90.0),
+        close_median_days: percentile(&close, 50.0),
+        close_p90_days: percentile(&close, 90.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const DAY: i64 = 86400;
+
+    #[test]
+    fn percentiles() {
+        assert_eq!(percentile(&[], 50.0), None);
+        assert_eq!(percentile(&[3.0], 90.0), Some(3.0));
+        assert_eq!(percentile(&[4.0, 1.0, 3.0, 2.0], 50.0), Some(2.5));
+        assert_eq!(percentile(&[1.0, 2.0, 3.0, 4.0, 5.0], 100.0), Some(5.0));
+        assert_eq!(percentile(&[0.0, 10.0], 90.0), Some(9.0));
+    }
+
+    #[test]
+    fn query_is_json() {
+        let q = query("o", "r", "pr", "2023-01-01", 50);
+        let v: Value = serde_json::from_str(&q).unwrap();
+        let q = v["query"].as_str().unwrap();
+        assert!(q.contains(
+            r#"search(query: "repo:o/r is:pr created:>=2023-01-01 sort:created-desc", type: ISSUE, first: 50)"#
+        ));
+        assert!(q.contains("reviews(first: 20)"));
+    }
+
+    #[test]
+    fn parse_search() {
+        let response = json!({ "data": { "search": { "nodes": [
+            {
+                "createdAt": "2023-01-01T00:00:00Z",
+                "closedAt": "2023-01-10T00:00:00Z",
+                "authorAssociation": "NONE",
+                "author": { "login": "user" },
+                "comments": { "nodes": [
+                    { "createdAt": "2023-01-02T00:00:00Z", "authorAssociation": "NONE", "author": { "login": "other" } },
+                    { "createdAt": "2023-01-02T06:00:00Z", "authorAssociation": "MEMBER", "author": { "login": "ci-helper", "__typename": "User" } },
+                    { "createdAt": "2023-01-03T00:00:00Z", "authorAssociation": "MEMBER", "author": { "login": "dev" } }
+                ] },
+                "reviews": { "nodes": [
+                    { "createdAt": "2023-01-02T12:00:00Z", "authorAssociation": "OWNER", "author": { "login": "boss" } }
+                ] }
+            },
+            {
+                "createdAt": "2023-02-01T00:00:00Z",
+                "closedAt": null,
+                "authorAssociation": "COLLABORATOR",
+                "author": { "login": "dev" },
+                "comments": { "nodes": [
+                    { "createdAt": "2023-02-02T00:00:00Z", "authorAssociation": "COLLABORATOR", "author": { "login": "dev" } }
+                ] }
+            },
+            {
+                "createdAt": "2023-03-01T00:00:00Z",
+                "closedAt": "2023-03-01T01:00:00Z",
+                "authorAssociation": "NONE",
+                "author": { "login": "dependabot", "__typename": "Bot" }
+            },
+            {}
+        ] } } });
+        let items = items(&response, &["ci-helper".to_string()]);
+        assert_eq!(items.len(), 3);
+        assert_eq!(
+            items[0].first_response,
+            Some(items[0].created + DAY + DAY / 2)
+        );
+        assert_eq!(items[0].closed, Some(items[0].created + 9 * DAY));
+        assert!(!items[0].by_maintainer);
+        // the author's own comment is not a response
+        assert_eq!(items[1].first_response, None);
+        assert!(items[1].by_maintainer);
+        assert!(items[2].by_bot);
+        assert!(!items[0].by_bot);
+        assert!(super::items(&json!({}), &[]).is_empty());
+    }
+
+    #[test]
+    fn summary() {
+        let item = |created: i64, response: Option<i64>, closed: Option<i64>, by_maintainer| Item {
+            created,
+            closed,
+            first_response: response,
+            by_maintainer,
+            by_bot: false,
+        };
+        let now = 100 * DAY;
+        let s = summarize(
+            &[
+                item(0, Some(DAY), Some(2 * DAY), false),
+                item(0, Some(3 * DAY), Some(10 * DAY), false),
+                // waiting since day 90
+                item(90 * DAY, None, None, false),
+                // ignored for responses
+                item(0, None, Some(4 * DAY), true),
+                // ignored altogether
+                Item {
+                    by_bot: true,
+                    ..item(0, Some(0), Some(0), false)
+                },
+            ],
+            now,
+        );
+        assert_eq!(s.items, 4);
+        assert_eq!(s.bot_items, 1);
+        assert_eq!(s.answered, 2);
+        assert_eq!(s.closed, 3);
+        assert_eq!(s.response_median_days, Some(3.0));
+        assert_eq!(s.close_median_days, Some(7.0));
+        assert_eq!(summarize(&[], now).response_median_days, None);
+    }
+}
@@ -0,0 +1,198 @@
+// share of the code on the default branch that went through approved pull requests
+use crate::metrics::bots;
+use crate::metrics::bugs::quoted;
+use serde::Serialize;
+use serde_json::Value;
+
+// commits fetched per request
+const PAGE: u32 = 100;
+
+// GraphQL query for the commits on the default branch since `since` (ISO 8601),
+// starting after the cursor of the previous page
+pub fn query(owner: &str, repo: &str, since: &str, after: Option<&str>) -> String {
+    let after = match after {
+        Some(a) => format!(", after: {}", quoted(a)),
+        None => String::new(),
+    };
+    let q = format!(
+        "query {{ repository(owner: {}, name: {}) {{ defaultBranchRef {{ target {{ ... on Commit {{ \
+         history(first: {}, since: {}{}) {{ pageInfo {{ hasNextPage endCursor }} nodes {{ \
+         oid additions parents {{ totalCount }} author {{ name email user {{ login }} }} \
+         associatedPullRequests(first: 1) {{ nodes {{ number merged \
+         reviews(states: APPROVED, first: 20) {{ nodes {{ author {{ login __typename }} }} }} }} }} \
+         }} }} }} }} }} }} }}",
+        quoted(owner),
+        quoted(repo),
+        PAGE,
+        quoted(since),
+        after
+    );
+    serde_json::json!({ "query": q }).to_string()
+}
+
+// commit on the default branch
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commit {
+    pub additions: u64,
+    // merged pull request the commit came with
+    pub pull_request: Option<u64>,
+    // by someone other than a bot
+    pub approved: bool,
+    // authored by an automated account, e.g. a dependency update
+    pub by_bot: bool,
+}
+
+// commits of a page, without merge commits whose changes are counted
+// with the merged commits, and the cursor of the next page if any
+// bots in `accounts` besides GitHub apps neither author nor approve
+pub fn parse(response: &Value, accounts: &[String]) -> Option<(Vec<Commit>, Option<String>)> {
+    let history = &response["data"]["repository"]["defaultBranchRef"]["target"]["history"];
+    let nodes = history["nodes"].as_array()?;
+    let commits = nodes
+        .iter()
+        .filter(|n| n["parents"]["totalCount"].as_u64().unwrap_or(1) <= 1)
+        .map(|n| {
+            let pr = n["associatedPullRequests"]["nodes"]
+                .as_array()
+                .and_then(|prs| prs.iter().find(|p| p["merged"].as_bool() == Some(true)));
+            let approved = pr
+                .and_then(|p| p["reviews"]["nodes"].as_array())
+                .is_some_and(|r| r.iter().any(|r| !bots::is_bot_user(&r["author"], accounts)));
+            let author = &n["author"];
+            let by_bot = bots::is_bot_identity(
+                author["name"].as_str().unwrap_or_default(),
+                author["email"].as_str().unwrap_or_default(),
+                accounts,
+            ) || bots::is_bot_user(&author["user"], accounts);
+            Commit {
+                additions: n["additions"].as_u64().unwrap_or(0),
+                pull_request: pr.and_then(|p| p["number"].as_u64()),
+                approved,
+                by_bot,
+            }
+        })
+        .collect();
+    let next = match history["pageInfo"]["hasNextPage"].as_bool() {
+        Some(true) => history["pageInfo"]["endCursor"]
+            .as_str()
+            .map(|c| c.to_string()),
+        _ => None,
+    };
+    Some((commits, next))
+}
+
+// lines added on the default branch by how they arrived,
+// without the commits of bots
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Review {
+    pub commits: usize,
+    pub bot_commits: usize,
+    pub additions: u64,
+    // through a merged pull request with an approving review
+    pub reviewed_additions: u64,
+    // through a merged pull request without one
+    pub unreviewed_pr_additions: u64,
+    // pushed without a pull request
+    pub direct_additions: u64,
+    pub pull_requests: usize,
+    pub approved_pull_requests: usize,
+}
+
+pub fn summarize(all: &[Commit]) -> Review {
+    let commits: Vec<&Commit> = all.iter().filter(|c| !c.by_bot).collect();
+    let mut r = Review {
+        commits: commits.len(),
+        bot_commits: all.len() - commits.len(),
+        ..Default::default()
+    };
+    let mut prs = std::collections::BTreeMap::new();
+    for c in commits {
+        r.additions += c.additions;
+        match c.pull_request {
+            Some(n) if c.approved => {
+                r.reviewed_additions += c.additions;
+                prs.insert(n, true);
+            }
+            Some(n) => {
+                r.unreviewed_pr_additions += c.additions;
+                prs.insert(n, false);
+            }
+            None => r.direct_additions += c.additions,
+        }
+    }
+    r.pull_requests = prs.len();
+    r.approved_pull_requests = prs.values().filter(|a| **a).count();
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn query_is_json() {
+        let q = query("o", "r", "2023-01-01T00:00:00Z", Some("abc 1"));
+        let v: Value = serde_json::from_str(&q).unwrap();
+        let q = v["query"].as_str().unwrap();
+        assert!(q.contains("repository(owner: \"o\", name: \"r\")"));
+        assert!(
+            q.contains("history(first: 100, since: \"2023-01-01T00:00:00Z\", after: \"abc 1\")")
+        );
+        // cursors are escaped like the other strings
+        let q = query("o", "r", "2023-01-01T00:00:00Z", Some("a\"b\\"));
+        let v: Value = serde_json::from_str(&q).unwrap();
+        assert!(v["query"]
+            .as_str()
+            .unwrap()
+            .contains("after: \"a\\\"b\\\\\")"));
+    }
+
+    #[test]
+    fn parse_page() {
+        let response = json!({ "data": { "repository": { "defaultBranchRef": { "target": { "history": {
+            "pageInfo": { "hasNextPage": true, "endCursor": "c1" },
+            "nodes": [
+                { "oid": "a", "additions": 10, "parents": { "totalCount": 1 },
+                  "associatedPullRequests": { "nodes": [ { "number": 1, "merged": true, "reviews": { "nodes": [
+                      { "author": { "login": "dev", "__typename": "User" } } ] } } ] } },
+                { "oid": "b", "additions": 5, "parents": { "totalCount": 2 },
+                  "associatedPullRequests": { "nodes": [ { "number": 1, "merged": true, "reviews": { "nodes": [] } } ] } },
+                { "oid": "c", "additions": 7, "parents": { "totalCount": 1 },
+                  "associatedPullRequests": { "nodes": [ { "number": 2, "merged": false, "reviews": { "nodes": [] } } ] } },
+                { "oid": "d", "additions": 3, "parents": { "totalCount": 1 },
+                  "associatedPullRequests": { "nodes": [ { "number": 3, "merged": true, "reviews": { "nodes": [
+                      { "author": { "login": "approver", "__typename": "Bot" } } ] } } ] } },
+                { "oid": "e", "additions": 40, "parents": { "totalCount": 1 },
+                  "author": { "name": "dependabot[bot]", "email": "49699333+dependabot[bot]@users.noreply.github.com", "user": null },
+                  "associatedPullRequests": { "nodes": [ { "number": 4, "merged": true, "reviews": { "nodes": [
+                      { "author": { "login": "dev", "__typename": "User" } } ] } } ] } },
+                { "oid": "f", "additions": 2, "parents": { "totalCount": 1 },
+                  "author": { "name": "Release", "email": "release@example.com", "user": { "login": "release-runner" } },
+                  "associatedPullRequests": { "nodes": [] } }
+            ]
+        } } } } } });
+        let (commits, next) = parse(&response, &["release-runner".to_string()]).unwrap();
+        assert_eq!(next.as_deref(), Some("c1"));
+        // the merge commit is skipped
+        assert_eq!(commits.len(), 5);
+        // an unmerged pull request doesn't count
+        assert_eq!(commits[1].pull_request, None);
+        // nor does the approval of a bot
+        assert!(!commits[2].approved);
+        assert!(commits[3].by_bot);
+        assert!(commits[4].by_bot);
+
+        let r = summarize(&commits);
+        assert_eq!(r.commits, 3);
+        assert_eq!(r.bot_commits, 2);
+        assert_eq!(r.additions, 20);
+        assert_eq!(r.reviewed_additions, 10);
+        assert_eq!(r.unreviewed_pr_additions, 3);
+        assert_eq!(r.direct_additions, 7);
+        assert_eq!(r.pull_requests, 2);
+        assert_eq!(r.approved_pull_requests, 1);
+
+        assert!(parse(&json!({ "data": null }), &[]).is_none());
+    }
+}
@@ -0,0 +1,338 @@
+// security posture checks in the style of OpenSSF Scorecard
+// see https://github.com/ossf/scorecard/blob/main/docs/checks.md
+use crate::metrics::files::File;
+use serde::Serialize;
+use std::path::Path;
+
+// outcome of one check, `passed` is None when it couldn't be decided
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub passed: Option<bool>,
+    pub detail: String,
+}
+
+impl Check {
+    pub fn new(name: &'static str, passed: Option<bool>, detail: &str) -> Check {
+        Check {
+            name,
+            passed,
+            detail: detail.to_string(),
+        }
+    }
+}
+
+// SECURITY.md at the root, in .github or in docs
+pub fn security_policy(files: &[File]) -> Check {
+    let found = files.iter().find(|f| {
+        let lower = f.path.to_ascii_lowercase();
+        ["", ".github/", "docs/"]
+            .iter()
+            .any(|d| lower == format!("{}security.md", d) || lower == format!("{}security.rst", d))
+    });
+    match found {
+        Some(f) => Check::new("security_policy", Some(true), &f.path),
+        None => Check::new("security_policy", Some(false), "no SECURITY.md"),
+    }
+}
+
+// Dependabot or Renovate configuration
+pub fn dependency_update_tool(files: &[File]) -> Check {
+    const CONFIGS: [&str; 8] = [
+        ".github/dependabot.yml",
+        ".github/dependabot.yaml",
+        "renovate.json",
+        "renovate.json5",
+        ".renovaterc",
+        ".renovaterc.json",
+        ".github/renovate.json",
+        ".github/renovate.json5",
+    ];
+    match files.iter().find(|f| CONFIGS.contains(&f.path.as_str())) {
+        Some(f) => Check::new("dependency_update_tool", Some(true), &f.path),
+        None => Check::new(
+            "dependency_update_tool",
+            Some(false),
+            "no Dependabot or Renovate configuration",
+        ),
+    }
+}
+
+// GitHub Actions workflow files
+pub fn workflows(files: &[File]) -> Vec<&File> {
+    files
+        .iter()
+        .filter(|f| {
+            f.path.starts_with(".github/workflows/")
+                && (f.path.ends_with(".yml") || f.path.ends_with(".yaml"))
+        })
+        .collect()
+}
+
+// `uses:` references of a workflow not pinned to a full commit hash
+// local actions and docker images pinned by digest are fine
+pub fn unpinned_actions(workflow: &str) -> Vec<String> {
+    workflow
+        .lines()
+        .filter_map(|l| {
+            let l = l.trim().trim_start_matches("- ").trim();
+            let v = l.strip_prefix("uses:")?.trim();
+            let v = v.split('#').next()?.trim().trim_matches(['"', '\'']);
+            if v.starts_with("./") || (v.starts_with("docker://") && v.contains("@sha256:")) {
+                return None;
+            }
+            let pinned = v
+                .rsplit_once('@')
+                .is_some_and(|(_, r)| r.len() == 40 && r.chars().all(|c| c.is_ascii_hexdigit()));
+            (!pinned).then(|| v.to_string())
+        })
+        .collect()
+}
+
+// contexts attackers control, which must not be expanded in scripts
+const UNTRUSTED: [&str; 12] = [
+    "github.event.issue.title",
+    "github.event.issue.body",
+    "github.event.pull_request.title",
+    "github.event.pull_request.body",
+    "github.event.comment.body",
+    "github.event.review.body",
+    "github.event.review_comment.body",
+    "github.event.pull_request.head.ref",
+    "github.event.pull_request.head.label",
+    "github.event.head_commit.message",
+    "github.event.head_commit.author",
+    "github.head_ref",
+];
+
+// lines of the `run:` scripts of a workflow: the value of a `run` key and the
+// lines indented below it
+// `with:`, `if:` and `env:` values are passed as data rather than spliced into
+// a shell script, so they are left out
+fn run_scripts(workflow: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut script_indent = None;
+    for l in workflow.lines() {
+        let indent = l.len() - l.trim_start().len();
+        if let Some(i) = script_indent {
+            if l.trim().is_empty() || indent > i {
+                lines.push(l);
+                continue;
+            }
+            script_indent = None;
+        }
+        let key = l.trim_start().trim_start_matches("- ");
+        if let Some(value) = key.strip_prefix("run:") {
+            lines.push(value);
+            script_indent = Some(l.len() - key.len());
+        }
+    }
+    lines
+}
+
+// dangerous patterns of a workflow: checking out the pull request's code
+// in a pull_request_target workflow, and untrusted input in run scripts
+pub fn dangerous_patterns(workflow: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    if workflow.contains("pull_request_target")
+        && workflow.contains("actions/checkout")
+        && (workflow.contains("github.event.pull_request.head.sha")
+            || workflow.contains("github.event.pull_request.head.ref")
+            || workflow.contains("github.head_ref"))
+    {
+        found.push("pull_request_target checks out the pull request".to_string());
+    }
+    for l in run_scripts(workflow) {
+        let mut rest = l;
+        while let Some(start) = rest.find("${{") {
+            let end = match rest[start..].find("}}") {
+                Some(e) => start + e,
+                None => break,
+            };
+            let expr = rest[start + 3..end].trim();
+            if let Some(u) = UNTRUSTED.iter().find(|u| expr.contains(*u)) {
+                found.push(format!("script injection of {}", u));
+            }
+            rest = &rest[end..];
+        }
+    }
+    found.sort();
+    found.dedup();
+    found
+}
+
+// every action pinned to a commit, unknown without workflows
+pub fn pinned_actions(root: &Path, workflows: &[&File]) -> Check {
+    if workflows.is_empty() {
+        return Check::new("pinned_actions", None, "no workflows");
+    }
+    let mut unpinned = Vec::new();
+    for w in workflows {
+        let text = std::fs::read_to_string(root.join(&w.path)).unwrap_or_default();
+        unpinned.extend(
+            unpinned_actions(&text)
+                .into_iter()
+                .map(|a| format!("{}: {}", w.path, a)),
+        );
+    }
+    if unpinned.is_empty() {
+        Check::new(
+            "pinned_actions",
+            Some(true),
+            "all actions pinned to a commit",
+        )
+    } else {
+        Check::new("pinned_actions", Some(false), &unpinned.join(", "))
+    }
+}
+
+// no workflow with dangerous patterns
+pub fn dangerous_workflow(root: &Path, workflows: &[&File]) -> Check {
+    let mut found = Vec::new();
+    for w in workflows {
+        let text = std::fs::read_to_string(root.join(&w.path)).unwrap_or_default();
+        found.extend(
+            dangerous_patterns(&text)
+                .into_iter()
+                .map(|p| format!("{}: {}", w.path, p)),
+        );
+    }
+    if found.is_empty() {
+        Check::new("dangerous_workflow", Some(true), "no dangerous patterns")
+    } else {
+        Check::new("dangerous_workflow", Some(false), &found.join(", "))
+    }
+}
+
+// signatures of the latest commits and annotated tags of the clone
+pub fn signatures(root: &Path, commits: usize) -> Result<(Check, Check), git2::Error> {
+    let repo = git2::Repository::open(root)?;
+    let mut walk = repo.revwalk()?;
+    walk.push_head()?;
+    let mut total = 0;
+    let mut signed = 0;
+    for oid in walk.take(commits) {
+        total += 1;
+        if repo.extract_signature(&oid?, None).is_ok() {
+            signed += 1;
+        }
+    }
+    let detail = format!("{} of the last {} commits signed", signed, total);
+    let commit_check = match total {
+        0 => Check::new("signed_commits", None, &detail),
+        _ => Check::new("signed_commits", Some(signed == total), &detail),
+    };
+
+    let mut tags = 0;
+    let mut signed_tags = 0;
+    repo.tag_foreach(|oid, _| {
+        if let Ok(tag) = repo.find_tag(oid) {
+            tags += 1;
+            let message = tag.message().unwrap_or_default();
+            if message.contains("-----BEGIN PGP SIGNATURE-----")
+                || message.contains("-----BEGIN SSH SIGNATURE-----")
+            {
+                signed_tags += 1;
+            }
+        }
+        true
+    })?;
+    let detail = format!("{} of {} annotated tags signed", signed_tags, tags);
+    let tag_check = match tags {
+        0 => Check::new("signed_tags", None, &detail),
+        _ => Check::new("signed_tags", Some(signed_tags == tags), &detail),
+    };
+    Ok((commit_check, tag_check))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(paths: &[&str]) -> Vec<File> {
+        paths
+            .iter()
+            .map(|p| File {
+                path: p.to_string(),
+                size: 1,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn policy_and_update_tool() {
+        assert_eq!(
+            security_policy(&files(&[".github/SECURITY.md"])).passed,
+            Some(true)
+        );
+        assert_eq!(
+            security_policy(&files(&["src/security.md"])).passed,
+            Some(false)
+        );
+        assert_eq!(
+            dependency_update_tool(&files(&[".github/dependabot.yml"])).passed,
+            Some(true)
+        );
+        assert_eq!(
+            dependency_update_tool(&files(&["renovate.json"])).passed,
+            Some(true)
+        );
+        assert_eq!(
+            dependency_update_tool(&files(&["README.md"])).passed,
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn actions() {
+        let w = "jobs:\n  build:\n    steps:\n\
+                 - uses: actions/checkout@v3\n\
+                 - uses: actions/setup-node@64ed1c7eab4cce3362f8c340dee64e5eaeef8f7c # v3\n\
+                 - uses: ./.github/actions/local\n\
+                 - uses: 'docker://alpine@sha256:abc'\n\
+                 - name: x\n        uses: \"owner/action@main\"\n";
+        assert_eq!(
+            unpinned_actions(w),
+            ["actions/checkout@v3", "owner/action@main"]
+        );
+    }
+
+    #[test]
+    fn dangerous() {
+        let w = "on: pull_request_target\njobs:\n  a:\n    steps:\n\
+                 - uses: actions/checkout@v3\n      with:\n        ref: ${{ github.event.pull_request.head.sha }}\n\
+                 - run: echo \"${{ github.event.issue.title }}\" ${{ secrets.TOKEN }}\n";
+        assert_eq!(
+            dangerous_patterns(w),
+            [
+                "pull_request_target checks out the pull request",
+                "script injection of github.event.issue.title"
+            ]
+        );
+        assert!(dangerous_patterns("on: push\nsteps:\n- run: echo ${{ github.sha }}\n").is_empty());
+
+        // multi-line scripts
+        let w = "steps:\n  - name: greet\n    run: |\n      echo hi\n\n      echo \"${{ github.head_ref }}\"\n  - run: echo ok\n";
+        assert_eq!(
+            dangerous_patterns(w),
+            ["script injection of github.head_ref"]
+        );
+
+        // untrusted input passed as data rather than expanded in a script
+        let w = [
+            "on: pull_request",
+            "jobs:",
+            "  a:",
+            "    if: ${{ github.event.pull_request.title != '' }}",
+            "    steps:",
+            "      - uses: actions/checkout@v3",
+            "        with:",
+            "          ref: ${{ github.event.pull_request.head.ref }}",
+            "      - run: echo \"$TITLE\"",
+            "        env:",
+            "          TITLE: ${{ github.event.pull_request.title }}",
+        ]
+        .join("\n");
+        assert!(dangerous_patterns(&w).is_empty());
+    }
+}
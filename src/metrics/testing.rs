@@ -0,0 +1,158 @@
+// test suites and continuous integration of a cloned repository
+use crate::metrics::files::{self, File};
+use serde::Serialize;
+use std::path::Path;
+
+// directories holding tests in the common ecosystems
+const TEST_DIRS: [&str; 7] = [
+    "test",
+    "tests",
+    "__tests__",
+    "spec",
+    "specs",
+    "testing",
+    "testdata",
+];
+
+// whether a source file is part of a test suite, by its path:
+// test directories, foo_test.go, test_foo.py, foo_test.py, foo.test.js, foo.spec.ts, FooTest.java
+pub fn is_test_file(path: &str) -> bool {
+    let mut parts: Vec<&str> = path.split('/').collect();
+    let name = parts.pop().unwrap_or_default();
+    if parts
+        .iter()
+        .any(|p| TEST_DIRS.contains(&p.to_ascii_lowercase().as_str()))
+    {
+        return true;
+    }
+    let stem = match name.split_once('.') {
+        Some((stem, _)) => stem,
+        None => return false,
+    };
+    let lower = name.to_ascii_lowercase();
+    stem.ends_with("_test")
+        || stem.starts_with("test_")
+        || stem.ends_with("Test")
+        || stem.ends_with("Tests")
+        || lower.contains(".test.")
+        || lower.contains(".spec.")
+}
+
+// CI services configured in the repository
+pub fn ci_systems(files: &[File]) -> Vec<&'static str> {
+    let mut found = Vec::new();
+    let has = |f: &dyn Fn(&str) -> bool| files.iter().any(|file| f(&file.path));
+    if has(&|p| {
+        p.starts_with(".github/workflows/") && (p.ends_with(".yml") || p.ends_with(".yaml"))
+    }) {
+        found.push("github_actions");
+    }
+    if has(&|p| p == ".travis.yml") {
+        found.push("travis");
+    }
+    if has(&|p| p == ".circleci/config.yml") {
+        found.push("circleci");
+    }
+    if has(&|p| p == ".gitlab-ci.yml") {
+        found.push("gitlab_ci");
+    }
+    found
+}
+
+// test and CI setup found in a clone
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Testing {
+    pub test_files: usize,
+    pub source_files: usize,
+    pub test_lines: usize,
+    pub source_lines: usize,
+    pub ci: Vec<&'static str>,
+}
+
+impl Testing {
+    // lines of test code per line of other source code
+    pub fn ratio(&self) -> f64 {
+        if self.source_lines == 0 {
+            0.0
+        } else {
+            self.test_lines as f64 / self.source_lines as f64
+        }
+    }
+}
+
+pub fn scan(root: &Path) -> Testing {
+    let files = files::list(root);
+    let mut t = Testing {
+        ci: ci_systems(&files),
+        ..Default::default()
+    };
+    for f in files.iter().filter(|f| files::language(&f.path).is_some()) {
+        let lines = files::lines(root, f);
+        if is_test_file(&f.path) {
+            t.test_files += 1;
+            t.test_lines += lines;
+        } else {
+            t.source_files += 1;
+            t.source_lines += lines;
+        }
+    }
+    t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_files() {
+        for p in [
+            "tests/cli.rs",
+            "src/__tests__/a.js",
+            "pkg/server_test.go",
+            "test_app.py",
+            "app/models_test.py",
+            "lib/index.test.js",
+            "src/app.spec.ts",
+            "src/main/java/FooTest.java",
+            "Spec/helper.rb",
+        ] {
+            assert!(is_test_file(p), "{}", p);
+        }
+        for p in [
+            "src/main.rs",
+            "lib/contest.js",
+            "src/attestation.py",
+            "Makefile",
+        ] {
+            assert!(!is_test_file(p), "{}", p);
+        }
+    }
+
+    #[test]
+    fn ci() {
+        let f = |p: &str| File {
+            path: p.to_string(),
+            size: 1,
+        };
+        assert_eq!(
+            ci_systems(&[f(".github/workflows/ci.yaml"), f(".gitlab-ci.yml")]),
+            ["github_actions", "gitlab_ci"]
+        );
+        assert_eq!(
+            ci_systems(&[f(".travis.yml"), f(".circleci/config.yml")]),
+            ["travis", "circleci"]
+        );
+        assert!(ci_systems(&[f(".github/workflows/README.md")]).is_empty());
+    }
+
+    #[test]
+    fn ratio() {
+        let t = Testing {
+            test_lines: 50,
+            source_lines: 200,
+            ..Default::default()
+        };
+        assert_eq!(t.ratio(), 0.25);
+        assert_eq!(Testing::default().ratio(), 0.0);
+    }
+}
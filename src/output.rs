@@ -0,0 +1,660 @@
+mod html;
+
+use crate::config::Profile;
+use crate::license::deps::Conflict;
+use crate::metrics::codebase::Codebase;
+use crate::metrics::plugins::{self, Outcome, Plugin};
+use crate::metrics::{Explain, Explanation, Metrics, METRICS};
+use clap::ValueEnum;
+use log::{debug, info};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+// output formats of the url command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// One JSON object per line
+    Ndjson,
+    /// A single JSON array
+    Json,
+    /// Comma separated values with a header row
+    Csv,
+    /// Markdown table, e.g. for pull request comments
+    Markdown,
+    /// Aligned table for the terminal
+    Table,
+    /// Self-contained HTML report
+    Html,
+}
+
+// scores of one module, written as one line of NDJSON
+// fields are serialized in declaration order
+#[derive(Debug, Serialize)]
+pub struct NetScore {
+    #[serde(rename = "URL")]
+    pub url: String,
+    #[serde(rename = "NET_SCORE", serialize_with = "score")]
+    pub net_score: f64,
+    // score of each metric, in the order of `METRICS`
+    #[serde(flatten, serialize_with = "metric_scores")]
+    pub metrics: Vec<f64>,
+    // scores of the metrics of plugins, by plugin name
+    #[serde(
+        rename = "PLUGIN_SCORES",
+        serialize_with = "scores",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub plugins: BTreeMap<String, f64>,
+
+    // scoring profile the net score was calculated with
+    #[serde(rename = "PROFILE")]
+    pub profile: String,
+
+    // outcome of each security check, null when it couldn't be decided
+    #[serde(rename = "SECURITY_CHECKS", skip_serializing_if = "BTreeMap::is_empty")]
+    pub security_checks: BTreeMap<String, Option<bool>>,
+
+    // whether each community item was found
+    #[serde(rename = "COMMUNITY_ITEMS", skip_serializing_if = "BTreeMap::is_empty")]
+    pub community_items: BTreeMap<String, bool>,
+
+    // dependencies with licenses the policy doesn't allow, with --license-depth
+    #[serde(rename = "LICENSE_CONFLICTS", skip_serializing_if = "Vec::is_empty")]
+    pub license_conflicts: Vec<Conflict>,
+
+    // size, languages and complexity of the cloned repository
+    #[serde(rename = "CODEBASE", skip_serializing_if = "Option::is_none")]
+    pub codebase: Option<Codebase>,
+
+    // raw inputs, formula and value of each metric
+    #[serde(rename = "EXPLAIN", skip_serializing_if = "Option::is_none")]
+    pub explain: Option<Explain>,
+
+    // report-only details
+    #[serde(skip)]
+    pub repository: Option<String>,
+}
+
+impl NetScore {
+    // calculate every metric of a module and combine them into the net score
+    // with the weights of the profile
+    // explanations of the metrics are kept if `explain` is set
+    pub fn new(
+        url: &str,
+        project: &dyn Metrics,
+        plugins: &[Box<dyn Plugin>],
+        profile: &Profile,
+        explain: bool,
+    ) -> NetScore {
+        info!("calculating score");
+        let metrics: Vec<f64> = METRICS.iter().map(|m| (m.score)(project)).collect();
+
+        // a plugin that fails scores 0
+        let mut target = project.target();
+        target.url = url.to_string();
+        let outcomes: Vec<(String, Outcome)> = plugins
+            .iter()
+            .map(|p| {
+                info!("calculating {} with plugin", p.name());
+                let outcome = p.score(&target).unwrap_or_else(|e| {
+                    info!("plugin {} failed: {}", p.name(), e);
+                    Outcome {
+                        score: 0.0,
+                        inputs: serde_json::json!({ "error": e }),
+                        formula: "0 if the plugin fails".to_string(),
+                    }
+                });
+                debug!("{}: {:.2}", p.name(), outcome.score);
+                (p.name().to_string(), outcome)
+            })
+            .collect();
+
+        let w = &profile.weights;
+        // metrics without weight are left out, so one that couldn't be
+        // measured (NaN) only affects the net score if it counts
+        let net_score = METRICS
+            .iter()
+            .zip(&metrics)
+            .filter(|(m, _)| w.get(m.name) > 0.0)
+            .map(|(m, x)| x * w.get(m.name))
+            .sum::<f64>()
+            + outcomes
+                .iter()
+                .map(|(name, o)| o.score * w.plugins.get(name).unwrap_or(&0.0))
+                .sum::<f64>();
+
+        // record the weights so the net score can be reproduced
+        let explain = explain.then(|| {
+            let mut e = project.explain();
+            for (name, o) in &outcomes {
+                e.insert(
+                    plugins::column(name),
+                    Explanation::new(o.inputs.clone(), &o.formula, o.score),
+                );
+            }
+            let weights = serde_json::to_value(w).unwrap_or_default();
+            e.insert(
+                "NET_SCORE".to_string(),
+                Explanation::new(
+                    serde_json::json!({ "profile": profile.name, "weights": weights }),
+                    "sum of weight * score over all metrics",
+                    net_score,
+                ),
+            );
+            e
+        });
+
+        NetScore {
+            url: url.to_string(),
+            net_score,
+            metrics,
+            plugins: outcomes.into_iter().map(|(n, o)| (n, o.score)).collect(),
+            profile: profile.name.clone(),
+            security_checks: project
+                .security_checks()
+                .into_iter()
+                .map(|c| (c.name.to_string(), c.passed))
+                .collect(),
+            community_items: project.community_items(),
+            license_conflicts: project.license_conflicts(),
+            codebase: project.codebase(),
+            explain,
+            repository: project.repository_url(),
+        }
+    }
+}
+
+impl NetScore {
+    // names of the score columns in tabular formats, in output order
+    pub fn columns() -> Vec<&'static str> {
+        let mut c = vec!["NET_SCORE"];
+        c.extend(METRICS.iter().map(|m| m.column));
+        c
+    }
+
+    // values of the score columns
+    pub fn values(&self) -> Vec<f64> {
+        let mut v = vec![self.net_score];
+        v.extend(&self.metrics);
+        v
+    }
+}
+
+// scores are rounded to 2 decimal places
+// NaN and infinity have no JSON representation, so they are written as null
+fn score<S: Serializer>(x: &f64, s: S) -> Result<S::Ok, S::Error> {
+    if x.is_finite() {
+        s.serialize_f64((x * 100.0).round() / 100.0)
+    } else {
+        s.serialize_none()
+    }
+}
+
+// score rounded like the others, for serializing collections of scores
+struct Score(f64);
+
+impl Serialize for Score {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        score(&self.0, s)
+    }
+}
+
+// metric scores keyed by their output names
+fn metric_scores<S: Serializer>(v: &[f64], s: S) -> Result<S::Ok, S::Error> {
+    s.collect_map(METRICS.iter().zip(v).map(|(m, x)| (m.column, Score(*x))))
+}
+
+// plugin scores, rounded like the others
+fn scores<S: Serializer>(m: &BTreeMap<String, f64>, s: S) -> Result<S::Ok, S::Error> {
+    s.collect_map(m.iter().map(|(k, v)| (k, Score(*v))))
+}
+
+// sort by net score, highest first
+// modules whose net score is not a number go last
+pub fn sort(scores: &mut [NetScore]) {
+    let key = |n: &NetScore| {
+        if n.net_score.is_nan() {
+            f64::NEG_INFINITY
+        } else {
+            n.net_score
+        }
+    };
+    scores.sort_by(|a, b| key(b).total_cmp(&key(a)));
+}
+
+// one JSON object per line
+pub fn write_ndjson<W: Write>(w: &mut W, scores: &[NetScore]) -> std::io::Result<()> {
+    for s in scores {
+        serde_json::to_writer(&mut *w, s)?;
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+// write scores in the requested format
+// color only applies to the terminal table
+pub fn write<W: Write>(
+    w: &mut W,
+    scores: &[NetScore],
+    format: Format,
+    color: bool,
+) -> std::io::Result<()> {
+    match format {
+        Format::Ndjson => write_ndjson(w, scores),
+        Format::Json => write_json(w, scores),
+        Format::Csv => write_csv(w, scores),
+        Format::Markdown => write_markdown(w, scores),
+        Format::Table => write_table(w, scores, color),
+        Format::Html => html::write_html(w, scores),
+    }
+}
+
+// pretty printed JSON array
+pub fn write_json<W: Write>(w: &mut W, scores: &[NetScore]) -> std::io::Result<()> {
+    serde_json::to_writer_pretty(&mut *w, scores)?;
+    writeln!(w)
+}
+
+// score with 2 decimal places, or `missing` if it is not a number
+fn cell(x: f64, missing: &str) -> String {
+    if x.is_finite() {
+        format!("{:.2}", x)
+    } else {
+        missing.to_string()
+    }
+}
+
+// plugins scored in any of the rows, each a column after the built-in scores
+fn plugin_names(scores: &[NetScore]) -> Vec<&str> {
+    let names: std::collections::BTreeSet<&str> = scores
+        .iter()
+        .flat_map(|s| s.plugins.keys().map(|n| n.as_str()))
+        .collect();
+    names.into_iter().collect()
+}
+
+fn headers(plugins: &[&str]) -> Vec<String> {
+    let mut h = vec!["URL".to_string()];
+    h.extend(NetScore::columns().into_iter().map(|c| c.to_string()));
+    h.extend(plugins.iter().map(|n| plugins::column(n)));
+    h
+}
+
+// values of the score columns, NaN for plugins the row wasn't scored with
+fn row(s: &NetScore, plugins: &[&str]) -> Vec<f64> {
+    let mut v = s.values();
+    v.extend(
+        plugins
+            .iter()
+            .map(|n| s.plugins.get(*n).copied().unwrap_or(f64::NAN)),
+    );
+    v
+}
+
+// quote fields containing separators as described in RFC 4180
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+pub fn write_csv<W: Write>(w: &mut W, scores: &[NetScore]) -> std::io::Result<()> {
+    let plugins = plugin_names(scores);
+    writeln!(w, "{}", headers(&plugins).join(","))?;
+    for s in scores {
+        let mut fields = vec![csv_field(&s.url)];
+        fields.extend(row(s, &plugins).into_iter().map(|x| cell(x, "")));
+        writeln!(w, "{}", fields.join(","))?;
+    }
+    Ok(())
+}
+
+pub fn write_markdown<W: Write>(w: &mut W, scores: &[NetScore]) -> std::io::Result<()> {
+    let plugins = plugin_names(scores);
+    let h = headers(&plugins);
+    writeln!(w, "| {} |", h.join(" | "))?;
+    // left align urls, right align scores
+    let mut align = vec![":---"];
+    align.extend(std::iter::repeat_n("---:", h.len() - 1));
+    writeln!(w, "| {} |", align.join(" | "))?;
+    for s in scores {
+        let url = s.url.replace('|', "\\|");
+        let mut cells = vec![format!("[{}]({})", url, url)];
+        cells.extend(row(s, &plugins).into_iter().map(|x| cell(x, "n/a")));
+        writeln!(w, "| {} |", cells.join(" | "))?;
+    }
+    Ok(())
+}
+
+// green for good scores, yellow for mediocre ones, red otherwise
+fn paint(text: &str, x: f64) -> String {
+    let code = if !x.is_finite() || x < 0.4 {
+        31
+    } else if x < 0.7 {
+        33
+    } else {
+        32
+    };
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
+
+pub fn write_table<W: Write>(w: &mut W, scores: &[NetScore], color: bool) -> std::io::Result<()> {
+    let plugins = plugin_names(scores);
+    let h = headers(&plugins);
+    let rows: Vec<Vec<(String, f64)>> = scores
+        .iter()
+        .map(|s| {
+            let mut cells = vec![(s.url.clone(), f64::NAN)];
+            cells.extend(row(s, &plugins).into_iter().map(|x| (cell(x, "n/a"), x)));
+            cells
+        })
+        .collect();
+
+    // width of each column is its widest cell
+    let widths: Vec<usize> = (0..h.len())
+        .map(|i| {
+            rows.iter()
+                .map(|r| r[i].0.chars().count())
+                .chain(std::iter::once(h[i].len()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let header: Vec<String> = h
+        .iter()
+        .zip(&widths)
+        .map(|(name, width)| format!("{:<width$}", name, width = width))
+        .collect();
+    writeln!(w, "{}", header.join("  ").trim_end())?;
+    let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    writeln!(w, "{}", rule.join("  "))?;
+
+    for row in rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, ((text, x), width))| {
+                if i == 0 {
+                    format!("{:<width$}", text, width = width)
+                } else {
+                    // pad before painting so escape codes don't affect alignment
+                    let padded = format!("{:>width$}", text, width = width);
+                    if color {
+                        paint(&padded, *x)
+                    } else {
+                        padded
+                    }
+                }
+            })
+            .collect();
+        writeln!(w, "{}", cells.join("  ").trim_end())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Explanation;
+
+    fn net_score(url: &str, net_score: f64) -> NetScore {
+        NetScore {
+            url: url.to_string(),
+            net_score,
+            metrics: (0..METRICS.len()).map(|i| i as f64 / 12.0).collect(),
+            plugins: BTreeMap::new(),
+            profile: "default".to_string(),
+            security_checks: BTreeMap::new(),
+            community_items: BTreeMap::new(),
+            license_conflicts: Vec::new(),
+            codebase: None,
+            explain: None,
+            repository: None,
+        }
+    }
+
+    fn ndjson(scores: &[NetScore]) -> String {
+        let mut out = Vec::new();
+        write_ndjson(&mut out, scores).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn field_order_and_precision() {
+        let line = ndjson(&[net_score("https://github.com/a/b", 0.66666)]);
+        assert!(line.starts_with(
+            "{\"URL\":\"https://github.com/a/b\",\"NET_SCORE\":0.67,\"RAMP_UP_SCORE\":0.0,\
+             \"CORRECTNESS_SCORE\":0.08,\"BUS_FACTOR_SCORE\":0.17,"
+        ));
+        assert!(line.ends_with(",\"PROFILE\":\"default\"}\n"));
+
+        // every metric follows the net score in table order
+        let at = |key: &str| line.find(&format!("\"{}\":", key)).unwrap();
+        let positions: Vec<usize> = NetScore::columns().into_iter().map(at).collect();
+        assert!(positions.windows(2).all(|p| p[0] < p[1]));
+    }
+
+    #[test]
+    fn url_escaping() {
+        let line = ndjson(&[net_score("https://x/\"a\"\\b", 0.0)]);
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["URL"], "https://x/\"a\"\\b");
+    }
+
+    #[test]
+    fn explain_output() {
+        let mut explained = net_score("a", 0.5);
+        assert!(!ndjson(&[net_score("a", 0.5)]).contains("EXPLAIN"));
+
+        let mut explain = Explain::new();
+        explain.insert(
+            "BUS_FACTOR_SCORE".to_string(),
+            Explanation::new(
+                serde_json::json!({ "mentionable_users": 3 }),
+                "2 * mentionable_users / (mentionable_users + 1) - 1",
+                0.5,
+            ),
+        );
+        explained.explain = Some(explain);
+        let value: serde_json::Value = serde_json::from_str(&ndjson(&[explained])).unwrap();
+        let bus = &value["EXPLAIN"]["BUS_FACTOR_SCORE"];
+        assert_eq!(bus["inputs"]["mentionable_users"], 3);
+        assert_eq!(bus["value"], 0.5);
+        assert!(bus["formula"]
+            .as_str()
+            .unwrap()
+            .contains("mentionable_users"));
+    }
+
+    #[test]
+    fn security_checks_output() {
+        assert!(!ndjson(&[net_score("a", 0.5)]).contains("SECURITY_CHECKS"));
+
+        let mut checked = net_score("a", 0.5);
+        checked
+            .security_checks
+            .insert("security_policy".to_string(), Some(true));
+        checked
+            .security_checks
+            .insert("branch_protection".to_string(), None);
+        let value: serde_json::Value = serde_json::from_str(&ndjson(&[checked])).unwrap();
+        assert_eq!(
+            value["SECURITY_CHECKS"],
+            serde_json::json!({ "branch_protection": null, "security_policy": true })
+        );
+    }
+
+    #[test]
+    fn community_items_output() {
+        assert!(!ndjson(&[net_score("a", 0.5)]).contains("COMMUNITY_ITEMS"));
+
+        let mut checked = net_score("a", 0.5);
+        checked
+            .community_items
+            .insert("contributing".to_string(), true);
+        checked.community_items.insert("funding".to_string(), false);
+        let value: serde_json::Value = serde_json::from_str(&ndjson(&[checked])).unwrap();
+        assert_eq!(
+            value["COMMUNITY_ITEMS"],
+            serde_json::json!({ "contributing": true, "funding": false })
+        );
+    }
+
+    #[test]
+    fn license_conflicts_output() {
+        assert!(!ndjson(&[net_score("a", 0.5)]).contains("LICENSE_CONFLICTS"));
+
+        let mut flagged = net_score("a", 0.5);
+        flagged.license_conflicts.push(Conflict {
+            path: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            license: Some("GPL-3.0-only".to_string()),
+            decision: crate::license::Decision::Deny,
+            rule: "deny: GPL-3.0-only".to_string(),
+        });
+        let value: serde_json::Value = serde_json::from_str(&ndjson(&[flagged])).unwrap();
+        let conflict = &value["LICENSE_CONFLICTS"][0];
+        assert_eq!(conflict["path"], serde_json::json!(["a", "b", "c"]));
+        assert_eq!(conflict["decision"], "deny");
+    }
+
+    #[test]
+    fn codebase_output() {
+        assert!(!ndjson(&[net_score("a", 0.5)]).contains("CODEBASE"));
+
+        let mut profiled = net_score("a", 0.5);
+        let mut codebase = Codebase {
+            files: 3,
+            source_files: 2,
+            source_lines: 120,
+            decision_density: 12.5,
+            ..Default::default()
+        };
+        codebase.lines_by_language.insert("Rust", 120);
+        profiled.codebase = Some(codebase);
+        let value: serde_json::Value = serde_json::from_str(&ndjson(&[profiled])).unwrap();
+        assert_eq!(value["CODEBASE"]["source_lines"], 120);
+        assert_eq!(value["CODEBASE"]["lines_by_language"]["Rust"], 120);
+        assert_eq!(value["CODEBASE"]["largest_files"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn plugin_scores_output() {
+        assert!(!ndjson(&[net_score("a", 0.5)]).contains("PLUGIN_SCORES"));
+
+        let mut custom = net_score("a", 0.5);
+        custom.plugins.insert("approved_vendor".to_string(), 1.0);
+        custom
+            .plugins
+            .insert("internal_review".to_string(), 1.0 / 3.0);
+        let value: serde_json::Value = serde_json::from_str(&ndjson(&[custom])).unwrap();
+        assert_eq!(
+            value["PLUGIN_SCORES"],
+            serde_json::json!({ "approved_vendor": 1.0, "internal_review": 0.33 })
+        );
+    }
+
+    #[test]
+    fn non_finite_scores() {
+        let line = ndjson(&[net_score("a", f64::NAN)]);
+        assert!(line.contains("\"NET_SCORE\":null"));
+        let line = ndjson(&[net_score("a", f64::INFINITY)]);
+        assert!(line.contains("\"NET_SCORE\":null"));
+    }
+
+    fn render(scores: &[NetScore], format: Format) -> String {
+        let mut out = Vec::new();
+        write(&mut out, scores, format, false).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn csv_output() {
+        let out = render(&[net_score("https://x/a,b", 0.5)], Format::Csv);
+        let mut lines = out.lines();
+        let header = lines.next().unwrap();
+        assert!(header.starts_with(
+            "URL,NET_SCORE,RAMP_UP_SCORE,CORRECTNESS_SCORE,BUS_FACTOR_SCORE,\
+             RESPONSIVE_MAINTAINER_SCORE,LICENSE_SCORE,"
+        ));
+        assert_eq!(header.split(',').count(), METRICS.len() + 2);
+        let row = lines.next().unwrap();
+        assert!(row.starts_with("\"https://x/a,b\",0.50,0.00,0.08,0.17,0.25,0.33,"));
+        assert_eq!(row.split(',').count(), METRICS.len() + 3);
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn plugin_columns() {
+        let mut a = net_score("a", 0.5);
+        a.plugins.insert("approved_vendor".to_string(), 1.0);
+        let b = net_score("b", 0.25);
+        let out = render(&[a, b], Format::Csv);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines[0],
+            format!("{},APPROVED_VENDOR_SCORE", headers(&[]).join(","))
+        );
+        assert!(lines[1].ends_with(",1.00"));
+        // b wasn't scored with the plugin
+        assert!(lines[2].ends_with(','));
+
+        let mut c = net_score("c", 0.5);
+        c.plugins.insert("approved_vendor".to_string(), 1.0);
+        let out = render(&[c], Format::Markdown);
+        assert!(out
+            .lines()
+            .next()
+            .unwrap()
+            .ends_with("| APPROVED_VENDOR_SCORE |"));
+    }
+
+    #[test]
+    fn markdown_output() {
+        let out = render(&[net_score("https://x/a|b", f64::NAN)], Format::Markdown);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("| :--- | ---: |"));
+        assert!(lines[2].starts_with("| [https://x/a\\|b](https://x/a\\|b) | n/a | 0.00 |"));
+    }
+
+    #[test]
+    fn table_output() {
+        let out = render(&[net_score("a", 0.5), net_score("b", 0.25)], Format::Table);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("URL  NET_SCORE  RAMP_UP_SCORE"));
+        assert!(lines[2].starts_with("a         0.50           0.00"));
+        assert!(!out.contains('\x1b'));
+
+        let mut colored = Vec::new();
+        write_table(&mut colored, &[net_score("a", 0.5)], true).unwrap();
+        let colored = String::from_utf8(colored).unwrap();
+        assert!(colored.contains("\x1b[33m     0.50\x1b[0m"));
+    }
+
+    #[test]
+    fn json_output() {
+        let out = render(&[net_score("a", 0.5), net_score("b", 0.25)], Format::Json);
+        let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(value.as_array().unwrap().len(), 2);
+        assert_eq!(value[1]["URL"], "b");
+    }
+
+    #[test]
+    fn sort_by_net_score() {
+        let mut scores = vec![
+            net_score("low", 0.1),
+            net_score("nan", f64::NAN),
+            net_score("high", 0.9),
+        ];
+        sort(&mut scores);
+        let urls: Vec<&str> = scores.iter().map(|s| s.url.as_str()).collect();
+        assert_eq!(urls, ["high", "low", "nan"]);
+    }
+}
@@ -0,0 +1,179 @@
+use super::{cell, NetScore};
+use crate::metrics::plugins;
+use std::io::Write;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 0.4em 0.6em; border-bottom: 1px solid #ddd; vertical-align: top; }
+.bar { background: #eee; width: 10em; height: 0.9em; display: inline-block; margin-right: 0.5em; }
+.fill { height: 100%; }
+.good { background: #2e7d32; }
+.fair { background: #f9a825; }
+.poor { background: #c62828; }
+.net { font-weight: bold; font-size: 1.1em; }
+dl { margin: 0; display: grid; grid-template-columns: auto auto; gap: 0 1em; }
+dt { color: #666; }
+dd { margin: 0; font-family: monospace; }
+";
+
+// escape text for use in html content and attributes
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// horizontal bar filled in proportion to a score in [0, 1]
+fn bar(x: f64) -> String {
+    let (width, class) = if x.is_finite() {
+        let class = if x < 0.4 {
+            "poor"
+        } else if x < 0.7 {
+            "fair"
+        } else {
+            "good"
+        };
+        (x.clamp(0.0, 1.0) * 100.0, class)
+    } else {
+        (0.0, "poor")
+    };
+    format!(
+        "<span class=\"bar\"><span class=\"fill {}\" style=\"width:{:.0}%;display:block\"></span></span>{}",
+        class,
+        width,
+        cell(x, "n/a")
+    )
+}
+
+// single self-contained page listing modules from most to least trustworthy
+pub fn write_html<W: Write>(w: &mut W, scores: &[NetScore]) -> std::io::Result<()> {
+    writeln!(w, "<!DOCTYPE html>")?;
+    writeln!(w, "<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(w, "<title>Module trustworthiness report</title>")?;
+    writeln!(w, "<style>{}</style>\n</head>\n<body>", STYLE)?;
+    writeln!(w, "<h1>Module trustworthiness report</h1>")?;
+    let profiles: std::collections::BTreeSet<&str> =
+        scores.iter().map(|s| s.profile.as_str()).collect();
+    writeln!(
+        w,
+        "<p>{} module(s), sorted by NET_SCORE (scoring profile: {}).</p>",
+        scores.len(),
+        escape(&profiles.into_iter().collect::<Vec<_>>().join(", "))
+    )?;
+
+    writeln!(w, "<table>\n<thead><tr>")?;
+    writeln!(
+        w,
+        "<th>#</th><th>Module</th><th>Scores</th><th>Raw inputs</th>"
+    )?;
+    writeln!(w, "</tr></thead>\n<tbody>")?;
+    for (rank, s) in scores.iter().enumerate() {
+        writeln!(w, "<tr>\n<td>{}</td>", rank + 1)?;
+
+        // module and the repository it was evaluated from
+        write!(w, "<td><a href=\"{0}\">{0}</a>", escape(&s.url))?;
+        if let Some(repo) = s.repository.as_deref().filter(|r| *r != s.url) {
+            write!(w, "<br>repository: <a href=\"{0}\">{0}</a>", escape(repo))?;
+        }
+        writeln!(w, "</td>")?;
+
+        // one bar per score, plugins after the built-in metrics
+        writeln!(w, "<td><dl>")?;
+        let builtin = NetScore::columns().into_iter().map(|c| c.to_string());
+        let custom = s.plugins.iter().map(|(n, x)| (plugins::column(n), *x));
+        for (name, x) in builtin.zip(s.values()).chain(custom) {
+            let class = if name == "NET_SCORE" {
+                " class=\"net\""
+            } else {
+                ""
+            };
+            writeln!(w, "<dt>{}</dt><dd{}>{}</dd>", escape(&name), class, bar(x))?;
+        }
+        writeln!(w, "</dl></td>")?;
+
+        // observations behind the scores
+        writeln!(w, "<td><dl>")?;
+        let metrics = s.explain.iter().flat_map(|e| e.iter());
+        for (_, e) in metrics.filter(|(name, _)| *name != "NET_SCORE") {
+            for (name, value) in &e.inputs {
+                let value = match value {
+                    serde_json::Value::String(v) => v.clone(),
+                    v => v.to_string(),
+                };
+                writeln!(w, "<dt>{}</dt><dd>{}</dd>", escape(name), escape(&value))?;
+            }
+        }
+        writeln!(w, "</dl></td>\n</tr>")?;
+    }
+    writeln!(w, "</tbody>\n</table>\n</body>\n</html>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{Explain, Explanation, METRICS};
+
+    #[test]
+    fn escapes_markup() {
+        assert_eq!(
+            escape("<a href=\"x\">&'</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn bar_width() {
+        assert!(bar(0.756).contains("fill good\" style=\"width:76%"));
+        assert!(bar(0.5).contains("fill fair\""));
+        assert!(bar(f64::NAN).contains("width:0%"));
+        assert!(bar(f64::NAN).ends_with("n/a"));
+    }
+
+    #[test]
+    fn report() {
+        let mut explain = Explain::new();
+        explain.insert(
+            "RAMP_UP_SCORE".to_string(),
+            Explanation::new(serde_json::json!({ "readme_lines": 120 }), "", 0.9),
+        );
+        explain.insert(
+            "LICENSE_SCORE".to_string(),
+            Explanation::new(serde_json::json!({ "spdx_id": "MIT" }), "", 1.0),
+        );
+        let score = NetScore {
+            url: "https://www.npmjs.com/package/<x>".to_string(),
+            net_score: 0.8,
+            metrics: vec![0.5; METRICS.len()],
+            plugins: [("approved_vendor".to_string(), 1.0)].into(),
+            profile: "default".to_string(),
+            security_checks: Default::default(),
+            community_items: Default::default(),
+            license_conflicts: Vec::new(),
+            codebase: None,
+            explain: Some(explain),
+            repository: Some("https://github.com/a/x".to_string()),
+        };
+        let mut out = Vec::new();
+        write_html(&mut out, &[score]).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.starts_with("<!DOCTYPE html>"));
+        assert!(out.contains("&lt;x&gt;"));
+        assert!(!out.contains("<x>"));
+        assert!(out.contains("<a href=\"https://github.com/a/x\">"));
+        assert!(out.contains("<dt>readme_lines</dt><dd>120</dd>"));
+        assert!(out.contains("<dt>spdx_id</dt><dd>MIT</dd>"));
+        assert!(out.contains("<dt>APPROVED_VENDOR_SCORE</dt><dd>"));
+        assert!(out.trim_end().ends_with("</html>"));
+    }
+}
@@ -1,4 +1,5 @@
 use assert_cmd::Command;
+use predicates::prelude::*;
 
 fn get_bin() -> Command {
     Command::new("./test_target/debug/ece461_team19_cli")
@@ -60,3 +61,87 @@ fn good_urls() {
     let mut cmd = get_bin();
     cmd.args(["url", "tests/url.txt"]).assert().success();
 }
+
+#[test]
+fn output_formats() {
+    let mut cmd = get_bin();
+    cmd.args(["url", "tests/empty.txt", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout("URL,NET_SCORE,RAMP_UP_SCORE,CORRECTNESS_SCORE,BUS_FACTOR_SCORE,RESPONSIVE_MAINTAINER_SCORE,LICENSE_SCORE,TESTING_SCORE,PINNING_SCORE,REVIEW_SCORE,MAINTENANCE_SCORE,VULNERABILITY_SCORE,SECURITY_SCORE,COMMUNITY_SCORE,POPULARITY_SCORE\n");
+
+    let mut cmd = get_bin();
+    cmd.args(["url", "tests/empty.txt", "--format", "json"])
+        .assert()
+        .success()
+        .stdout("[]\n");
+
+    let mut cmd = get_bin();
+    cmd.args(["url", "tests/empty.txt", "--format", "xml"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn explain_flag() {
+    let mut cmd = get_bin();
+    cmd.args(["url", "tests/empty.txt", "--explain"])
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn scoring_profiles() {
+    let mut cmd = get_bin();
+    cmd.args([
+        "url",
+        "tests/empty.txt",
+        "--config",
+        "tests/good_profiles.toml",
+    ])
+    .args(["--profile", "compliance"])
+    .assert()
+    .success();
+
+    let mut cmd = get_bin();
+    cmd.args(["url", "tests/empty.txt", "--profile", "missing"])
+        .assert()
+        .failure();
+
+    let mut cmd = get_bin();
+    cmd.args(["url", "tests/empty.txt", "--config", "notafile.toml"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn config_show() {
+    let mut cmd = get_bin();
+    cmd.args(["config", "show", "--config", "tests/good_profiles.toml"])
+        .env("GITHUB_TOKEN", "secret")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("[profiles.compliance]"))
+        .stdout(predicates::str::contains("secret").not());
+
+    let mut cmd = get_bin();
+    cmd.args(["config", "show", "--config", "notafile.toml"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn license_policy() {
+    let mut cmd = get_bin();
+    cmd.args(["url", "tests/empty.txt"])
+        .args(["--license-policy", "tests/license_policy.toml"])
+        .assert()
+        .success();
+
+    let mut cmd = get_bin();
+    cmd.args(["url", "tests/empty.txt"])
+        .args(["--license-policy", "tests/good_profiles.toml"])
+        .assert()
+        .failure();
+}